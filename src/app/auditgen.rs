@@ -0,0 +1,322 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    cell::Cell,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+use rfd::
+{
+    FileDialog,
+    MessageButtons,
+    MessageDialogResult,
+    MessageLevel
+};
+
+use slint::
+{
+    ComponentHandle,
+    ModelRc,
+    SharedString,
+    StandardListViewItem
+};
+
+use super::
+{
+    MainWindow,
+    CHECKED,
+    popup,
+    show_report
+};
+
+use crate::
+{
+    audit::
+    {
+        Audit,
+        MapAudit
+    },
+    casecheck::CaseCheck,
+    current_dir_path,
+    package::package_maps,
+    rescheck::ResourceCheck,
+    resources::create_res,
+    with_controller
+};
+
+pub(crate) struct Controller
+{
+    maps_dir: PathBuf
+}
+
+thread_local!
+{
+    static CTRL: Cell<Option<Controller>> = const { Cell::new( None ) };
+}
+
+fn make_audit_row(map: &MapAudit) -> ModelRc<StandardListViewItem>
+{
+    let mark = |b: bool| StandardListViewItem::from( if b { CHECKED } else { "" } );
+    let missing: Vec<_> = map.missing_references().map( |r| r.path.as_str() ).collect();
+
+    ModelRc::from
+    ([
+        StandardListViewItem::from( map.bsp.as_str() ),
+        mark( map.cfg ),
+        mark( map.skill_cfg ),
+        mark( map.motd ),
+        mark( map.res ),
+        StandardListViewItem::from( missing.join( ", " ).as_str() )
+    ])
+}
+/// Lists the invalid BSPs, missing referenced files and orphaned files under the table.
+fn audit_notes(audit: &Audit) -> String
+{
+    let invalid = audit
+        .maps
+        .iter()
+    .filter_map( |m| Some( format!( "{}.bsp: {}", m.bsp, m.error.as_ref()? ) ) );
+
+    let missing = audit
+        .missing_references()
+    .map( |r| format!( "{}: '{} {}' points to a file that can't be found", r.cfg, r.cvar, r.path ) );
+
+    let orphans = audit
+        .orphans
+        .iter()
+    .map( |o| format!( "{o}: no matching BSP" ) );
+
+    invalid.chain( missing ).chain( orphans ).collect::<Vec<_>>().join( "\n" )
+}
+
+/// Lists the files each map is missing or has shadowed by a higher priority content folder.
+fn check_notes(check: &ResourceCheck) -> String
+{
+    let mut notes = vec![format!( "Searched {}", check.searched.join( ", " ) )];
+
+    for map in &check.maps
+    {
+        if let Some( error ) = &map.error
+        {
+            notes.push( format!( "{}.bsp: {error}", map.bsp ) );
+        }
+
+        notes.extend( map.missing().map( |r| format!( "{}: {} is missing ({})", map.bsp, r.path, r.source ) ) );
+        notes.extend( map.shadowed().map( |r| format!( "{}: {} in {} shadows {}",
+            map.bsp, r.path, r.found_in.as_deref().unwrap_or_default(), r.shadowed.join( ", " ) ) ) );
+    }
+
+    if notes.len() == 1
+    {
+        notes.push( "Every resource was found.".into() );
+    }
+
+    notes.join( "\n" )
+}
+
+impl Controller
+{
+    pub fn new(app: &MainWindow) -> Self
+    {
+        // ========== Map Audit Callback Bindings ==========
+        let app_weak = app.as_weak();
+        app.on_audit_change_folder( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_change_folder( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_audit_refresh( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.refresh( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_audit_generate_res( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_generate_res( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_audit_check_resources( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_check_resources( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_audit_check_case( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_check_case( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_audit_package( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_package( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_audit_dropped( move |path|
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_dropped( path.as_str(), app ) );
+        });
+
+        let this = Self { maps_dir: current_dir_path!() };
+        this.refresh( app );
+
+        this
+    }
+
+    pub fn register(self)
+    {
+        CTRL.set( Some( self ) );
+    }
+    // ========== Map Audit Handlers ==========
+    fn on_change_folder(&mut self, app: &MainWindow)
+    {
+        let Some( selected ) = FileDialog::new().set_title( "Select a maps folder" ).pick_folder()
+        else
+        {
+            return;
+        };
+
+        self.maps_dir = selected;
+        self.refresh( app );
+    }
+
+    fn on_dropped(&mut self, path: &str, app: &MainWindow)
+    {
+        let path = Path::new( path );
+
+        if !path.is_dir()
+        {
+            popup( "Invalid folder", "Drop a maps folder to audit it.", MessageLevel::Warning, MessageButtons::Ok );
+            return;
+        }
+
+        self.maps_dir = path.to_path_buf();
+        self.refresh( app );
+    }
+
+    fn on_generate_res(&mut self, app: &MainWindow)
+    {
+        show_report( "RES", create_res( &self.maps_dir, &[] ) );
+        self.refresh( app );
+    }
+
+    fn on_check_resources(&mut self, app: &MainWindow)
+    {
+        match ResourceCheck::run( &self.maps_dir, &[] )
+        {
+            Ok( check ) => app.set_audit_notes( SharedString::from( check_notes( &check ) ) ),
+            Err( e ) =>
+            {
+                popup( "Could not check resources", &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+            }
+        }
+    }
+
+    fn on_check_case(&mut self, app: &MainWindow)
+    {
+        let check =
+        match CaseCheck::run( &self.maps_dir, &[] )
+        {
+            Ok( check ) => check,
+            Err( e ) =>
+            {
+                popup( "Could not check case", &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+                return;
+            }
+        };
+
+        let mut notes = vec![format!( "Checked {} reference(s), {} only match when case is ignored", check.checked, check.issues.len() )];
+        notes.extend( check.issues.iter().map( |i| format!( "{}: '{}' is '{}' on disk", i.file.display(), i.reference, i.actual ) ) );
+        app.set_audit_notes( SharedString::from( notes.join( "\n" ) ) );
+
+        if check.issues.is_empty()
+        {
+            return;
+        }
+
+        let confirm = popup( "Fix case",
+            &format!( "{} reference(s) only match their file when case is ignored, so they break on Linux servers.\n\
+            Rewrite them to match the files on disk?", check.issues.len() ),
+            MessageLevel::Warning, MessageButtons::YesNo );
+
+        if confirm == MessageDialogResult::Yes
+        {
+            show_report( "fixed", check.fix() );
+            self.refresh( app );
+        }
+    }
+
+    fn on_package(&mut self, app: &MainWindow)
+    {
+        let Some( out_dir ) = FileDialog::new()
+            .set_title( "Select where to save the map packages" )
+            .set_directory( &self.maps_dir )
+        .pick_folder()
+        else
+        {
+            return;
+        };
+
+        match package_maps( &self.maps_dir, &[], &out_dir )
+        {
+            Ok( ( report, manifests ) ) =>
+            {
+                let notes: Vec<_> = manifests
+                    .iter()
+                    .flat_map( |m| m.missing.iter().map( move |missing| format!( "{}: {missing} is missing, so it was not packaged", m.map ) ) )
+                .collect();
+
+                app.set_audit_notes( SharedString::from( notes.join( "\n" ) ) );
+                show_report( "package", Ok( report ) );
+            }
+
+            Err( e ) => show_report( "package", Err( e ) )
+        }
+    }
+
+    fn refresh(&self, app: &MainWindow)
+    {
+        app.set_audit_folder( SharedString::from( self.maps_dir.display().to_string() ) );
+
+        let audit =
+        match Audit::run( &self.maps_dir )
+        {
+            Ok( audit ) => audit,
+            Err( e ) =>
+            {
+                app.set_audit_rows( ModelRc::default() );
+                app.set_audit_notes( SharedString::from( format!( "Could not read the folder: {e}" ) ) );
+                return;
+            }
+        };
+
+        let rows: Vec<_> = audit.maps.iter().map( make_audit_row ).collect();
+
+        app.set_audit_rows( ModelRc::from( rows.as_slice() ) );
+        app.set_audit_notes( SharedString::from( audit_notes( &audit ) ) );
+    }
+}
@@ -18,14 +18,18 @@ along with this program. If not, see <https://www.gnu.org/licenses/>.
 use std::
 {
     cell::Cell,
-    fs,
-    path::PathBuf,
+    path::
+    {
+        Path,
+        PathBuf
+    },
 };
 
 use rfd::
 {
     FileDialog,
     MessageButtons,
+    MessageDialogResult,
     MessageLevel
 };
 
@@ -51,12 +55,15 @@ use crate::{
         collect_bsp_items,
         current_bsp_whitelist,
         load_cvar_presets,
-    }, current_dir_path, cvar::parse_cfg, prelude::*, utils, with_controller
+        show_report,
+    }, backup::BackupStore, current_dir_path, lint, prelude::*, schema::schema, utils, with_controller
 };
 
 pub(crate) struct Controller
 {
     bsp_dir: PathBuf,
+    /// The write being previewed and the changes shown for it, applied as they are.
+    pending_write: Option<(Cfg, Vec<FileChange>)>,
     default_cvar_cache: SharedString,
     skill_cvar_cache: SharedString
 }
@@ -81,6 +88,7 @@ impl Controller
         let this = Self
         {
             bsp_dir: bsp_path,
+            pending_write: None,
             default_cvar_cache: SharedString::new(),
             skill_cvar_cache: SharedString::new()
         };
@@ -98,6 +106,12 @@ impl Controller
             with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_dropped( path.as_str(), app ) );
         });
 
+        let app_weak = app.as_weak();
+        app.on_cvar_edited( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.refresh_lint( app ) );
+        });
+
         let app_weak = app.as_weak();
         app.on_bsp_toggled( move |idx|
         {
@@ -146,6 +160,54 @@ impl Controller
             with_controller!( app_weak, CTRL, |ctrl, app| ctrl.write_cfg( app, WriteType::DELETE ) );
         });
 
+        let app_weak = app.as_weak();
+        app.on_set_cfg( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.write_cfg( app, WriteType::SET ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_unset_cfg( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.write_cfg( app, WriteType::UNSET ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_merge_cfg( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.write_cfg( app, WriteType::MERGE ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_minimize_cfg( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.write_cfg( app, WriteType::MINIMIZE ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_show_overrides( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.show_overrides( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_undo_cfg( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_undo( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_cfg_apply_preview( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_apply_preview( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_cfg_cancel_preview( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_cancel_preview( app ) );
+        });
+
         this
     }
 
@@ -180,12 +242,12 @@ impl Controller
             return;
         }
 
-        match fs::File::open( path )
+        match CfgDocument::from_file( Path::new( path ) )
         {
-            Ok( file ) =>
+            Ok( doc ) =>
             {
-                let cvars = parse_cfg( file );
-                app.set_cvar_text( SharedString::from( cvars.join( "\n" ) ) );
+                app.set_cvar_text( SharedString::from( doc.to_string() ) );
+                self.refresh_lint( app );
             }
 
             Err( e ) =>
@@ -232,6 +294,7 @@ impl Controller
 
             current.push_str( &item.text );
             app.set_cvar_text( current );
+            self.refresh_lint( app );
         }
 
         app.set_cvar_current( -1 );
@@ -255,6 +318,7 @@ impl Controller
 
         app.set_cvar_presets( ModelRc::from( load_cvar_presets( is_skill ).as_slice() ) );
         app.set_cvar_current( -1 );
+        self.refresh_lint( app );
     }
 
     fn on_change_folder(&mut self, app: &MainWindow)
@@ -276,9 +340,20 @@ impl Controller
         self.bsp_dir = selected.clone();
         app.set_bsp_items( ModelRc::from( collect_bsp_items( &selected ).as_slice() ) );
         app.set_cvar_text( SharedString::new() );
+        self.refresh_lint( app );
+    }
+    /// Checks the CVar text box and lists any problems under it.
+    fn refresh_lint(&self, app: &MainWindow)
+    {
+        let diagnostics: Vec<_> = lint::lint( &app.get_cvar_text(), schema() )
+            .iter()
+            .map( |d| format!( "Line {d}" ) )
+        .collect();
+
+        app.set_cfg_lint_text( SharedString::from( diagnostics.join( "\n" ) ) );
     }
 
-    fn write_cfg(&self, app: &MainWindow, wt: WriteType)
+    fn cfg_from_ui(&self, app: &MainWindow, wt: WriteType) -> Cfg
     {
         Cfg
         {
@@ -287,6 +362,132 @@ impl Controller
             is_skillcfg: app.get_skill_cfg(),
             bspdir: self.bsp_dir.clone(),
             bspwhitelist: current_bsp_whitelist( app )
-        }.create();
+        }
+    }
+    /// Shows a diff of what the write would change, the write happens once the user applies it.
+    fn write_cfg(&mut self, app: &MainWindow, wt: WriteType)
+    {
+        let cfg = self.cfg_from_ui( app, wt );
+
+        let changes =
+        match cfg.preview()
+        {
+            Ok( changes ) => changes,
+            Err( e ) =>
+            {
+                show_report( "CFG", Err( e ) );
+                return;
+            }
+        };
+
+        let diff: String = changes.iter().map( FileChange::unified_diff ).collect();
+
+        if diff.is_empty()
+        {
+            popup( "Nothing to change", "The CFG files already match, nothing will be written.",
+                MessageLevel::Info, MessageButtons::Ok );
+
+            return;
+        }
+
+        self.pending_write = Some( ( cfg, changes ) );
+        app.set_cfg_preview_text( SharedString::from( diff ) );
+        app.set_cfg_preview_can_apply( true );
+        app.set_cfg_preview_visible( true );
+    }
+    /// Lists the CVars each selected BSP's cfg changes from the server defaults, in the preview pane.
+    fn show_overrides(&mut self, app: &MainWindow)
+    {
+        let overrides =
+        match self.cfg_from_ui( app, WriteType::MINIMIZE ).overrides()
+        {
+            Ok( overrides ) => overrides,
+            Err( e ) =>
+            {
+                popup( "Failed to read CFG files", &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+                return;
+            }
+        };
+
+        if overrides.is_empty()
+        {
+            popup( "No CFG files", "None of the selected BSPs have a CFG file yet.",
+                MessageLevel::Info, MessageButtons::Ok );
+
+            return;
+        }
+
+        let text: String = overrides
+            .iter()
+            .map( |( cfg_name, cvars )|
+            {
+                let cvars: String = cvars.iter().map( |c| format!( "    {c}\n" ) ).collect();
+                let name = cfg_name.file_name().unwrap_or_default().to_string_lossy();
+
+                if cvars.is_empty()
+                {
+                    format!( "{name}: same as the defaults\n\n" )
+                }
+                else
+                {
+                    format!( "{name}:\n{cvars}\n" )
+                }
+            })
+        .collect();
+
+        self.pending_write = None;
+        app.set_cfg_preview_text( SharedString::from( text ) );
+        app.set_cfg_preview_can_apply( false );
+        app.set_cfg_preview_visible( true );
+    }
+
+    /// Puts back the CFG files from before the last write, after asking the user.
+    fn on_undo(&mut self, _app: &MainWindow)
+    {
+        let store = BackupStore::open();
+
+        let set =
+        match store.last()
+        {
+            Ok( Some( set ) ) => set,
+            Ok( None ) =>
+            {
+                popup( "Nothing to undo", "There are no backups to restore.", MessageLevel::Info, MessageButtons::Ok );
+                return;
+            }
+
+            Err( e ) =>
+            {
+                popup( "Failed to read backups", &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+                return;
+            }
+        };
+
+        let confirm = popup( "Undo last operation",
+            &format!( "Undo {} on {} file(s)?\nThe files will be put back as they were before.", set.manifest.operation, set.manifest.files.len() ),
+            MessageLevel::Warning, MessageButtons::YesNo );
+
+        if confirm == MessageDialogResult::Yes
+        {
+            show_report( "restored", store.restore_last() );
+        }
+    }
+
+    fn on_apply_preview(&mut self, app: &MainWindow)
+    {
+        app.set_cfg_preview_visible( false );
+        app.set_cfg_preview_text( SharedString::new() );
+
+        if let Some( ( cfg, changes ) ) = self.pending_write.take()
+        {
+            show_report( "CFG", cfg.apply( changes, app.get_cfg_all_or_nothing() ) );
+        }
+    }
+
+    fn on_cancel_preview(&mut self, app: &MainWindow)
+    {
+        self.pending_write = None;
+        app.set_cfg_preview_visible( false );
+        app.set_cfg_preview_text( SharedString::new() );
     }
 }
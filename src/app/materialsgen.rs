@@ -18,7 +18,9 @@ along with this program. If not, see <https://www.gnu.org/licenses/>.
 use std::
 {
     cell::Cell,
+    io,
     path::Path,
+    thread
 };
 
 use rfd::
@@ -32,18 +34,33 @@ use rfd::
 use slint::
 {
     ComponentHandle,
+    Image,
     ModelRc,
-    StandardListViewItem
+    Rgba8Pixel,
+    SharedPixelBuffer
 };
 
 use super::
 {
     MainWindow,
+    WadTexture,
     make_row,
     popup
 };
 
-use crate::with_controller;
+use crate::
+{
+    bsp::Bsp,
+    wad::
+    {
+        self,
+        LumpKind,
+        Wad,
+        EXT_WAD,
+        mip_for
+    },
+    with_controller
+};
 
 use crate::prelude::*;
 
@@ -51,9 +68,17 @@ use crate::prelude::*;
 pub(crate) struct Controller
 {
     material_rows: Vec<(String, String)>,
-    wad_textures: Vec<String>
+    /// Textures to pick from, from a WAD or a map's texture lump, and whether the map draws any faces with them.
+    textures: Vec<(WadTexture, bool)>,
+    /// File name of the WAD or map the textures are from.
+    textures_from: String,
+    /// Counts the WADs and maps picked, so textures that finish loading after another was picked are dropped.
+    texture_load: u32
 }
 
+/// Thumbnails are decoded from the smallest mip level at least this size, which keeps large WADs quick to list.
+const THUMBNAIL_SIZE: u32 = 64;
+
 thread_local!
 {
     static CTRL: Cell<Option<Controller>> = const { Cell::new( None ) };
@@ -68,13 +93,98 @@ fn sync_materials(rows: &[(String, String)], app: &MainWindow)
     app.set_material_rows( ModelRc::from( items.as_slice() ) );
 }
 
-fn sync_wad_textures(textures: &[String], app: &MainWindow)
+/// A texture to pick from, with its thumbnail decoded on a worker thread. Slint images can only be made on the UI thread,
+/// so the thumbnail is turned into one once the list is handed back.
+struct Listed
+{
+    name: String,
+    info: String,
+    /// Whether the map draws any faces with it, always true for WAD lumps.
+    used: bool,
+    thumbnail: io::Result<wad::Image>
+}
+
+impl Listed
 {
-    let items: Vec<_> = textures.iter()
-        .map( |n| StandardListViewItem::from( n.as_str() ) )
+    fn into_row(self) -> (WadTexture, bool)
+    {
+        let thumbnail = self.thumbnail
+            .map( |image| Image::from_rgba8( SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice( &image.rgba, image.width, image.height ) ) )
+        .unwrap_or_default();
+
+        ( WadTexture { name: self.name.into(), info: self.info.into(), thumbnail }, self.used )
+    }
+}
+
+fn wad_textures(wad: &Wad) -> Vec<Listed>
+{
+    wad.lumps.iter()
+        .map( |lump|
+        {
+            let info =
+            match lump.kind
+            {
+                LumpKind::MipTex => format!( "{}x{}, {} bytes", lump.width, lump.height, lump.size ),
+                kind => format!( "{kind}, {}x{}, {} bytes", lump.width, lump.height, lump.size )
+            };
+
+            let thumbnail = wad.decode( lump, mip_for( lump.width, lump.height, THUMBNAIL_SIZE ) );
+
+            Listed { name: lump.name.clone(), info, used: true, thumbnail }
+        })
+    .collect()
+}
+/// Lists the texture lump of a map. Textures that aren't embedded are looked up in the worldspawn WADs,
+/// searching the map's own content folder before the game's.
+fn map_textures(path: &Path) -> io::Result<Vec<Listed>>
+{
+    let bsp = Bsp::open( path )?;
+
+    let dirs: Vec<_> = path
+        .parent()
+        .and_then( Path::parent )
+        .map( Path::to_path_buf )
+        .into_iter()
+        .chain( GameRoots::from_config().roots )
+    .collect();
+
+    let wads: Vec<_> = bsp.wads()
+        .into_iter()
+        .filter_map( |name|
+        {
+            let wad = dirs.iter().map( |d| d.join( &name ) ).find( |p| p.is_file() )?;
+            Wad::open( &wad ).ok().map( |wad| ( name, wad ) )
+        })
     .collect();
 
-    app.set_material_wad_textures( ModelRc::from( items.as_slice() ) );
+    let textures = bsp.textures()?
+        .into_iter()
+        .map( |texture|
+        {
+            let mip = mip_for( texture.width, texture.height, THUMBNAIL_SIZE );
+
+            let ( source, thumbnail ) =
+            if texture.embedded
+            {
+                ( "embedded".to_string(), bsp.decode_texture( &texture, mip ) )
+            }
+            else
+            {
+                match wads.iter().find_map( |( name, wad )| wad.find( &texture.name ).map( |lump| ( name, wad, lump ) ) )
+                {
+                    Some( ( name, wad, lump ) ) => ( format!( "external, from {name}" ), wad.decode( lump, mip ) ),
+                    None => ( "external, not in the map's WADs".to_string(), Err( io::ErrorKind::NotFound.into() ) )
+                }
+            };
+
+            let unused = if texture.used { "" } else { ", unused" };
+            let info = format!( "{}x{}, {source}{unused}", texture.width, texture.height );
+
+            Listed { name: texture.name, info, used: texture.used, thumbnail }
+        })
+    .collect();
+
+    Ok( textures )
 }
 
 impl Controller
@@ -110,6 +220,12 @@ impl Controller
             with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_wad_texture_selected( app, idx ) );
         });
 
+        let app_weak = app.as_weak();
+        app.on_wad_filter_changed( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.sync_textures( app ) );
+        });
+
         let app_weak = app.as_weak();
         app.on_kind_changed( move |idx|
         {
@@ -170,33 +286,14 @@ impl Controller
             return;
         }
 
-        if path.has_extension( &["wad"] )
+        if path.has_extension( &[EXT_WAD, EXT_BSP] )
         {
-            match read_texture_names( path )
-            {
-                Ok( names ) =>
-                {
-                    self.wad_textures = names;
-                    sync_wad_textures( &self.wad_textures, app );
-
-                    let filename = Path::new( path ).file_name()
-                        .map( |n| n.to_string_lossy().to_string() )
-                        .unwrap_or_default();
-                    app.set_material_wad_button_text( format!( "Set WAD: {filename}" ).into() );
-                }
-
-                Err( e ) =>
-                {
-                    popup( "Error loading WAD", &format!( "Could not read texture names from WAD.\nReason: {e}" ),
-                        MessageLevel::Error, MessageButtons::Ok );
-                }
-            }
-
+            self.set_textures( app, Path::new( path ) );
             return;
         }
 
         popup( "Invalid file",
-            "The dropped file is not a recognized format (.txt, .wad).",
+            "The dropped file is not a recognized format (.txt, .wad, .bsp).",
             MessageLevel::Warning, MessageButtons::Ok );
     }
 
@@ -232,44 +329,113 @@ impl Controller
 
     fn on_load_wad(&mut self, app: &MainWindow)
     {
-        let Some( file ) = FileDialog::new().add_filter( "WAD Files", &["wad"] ).pick_file()
+        let Some( file ) = FileDialog::new()
+            .add_filter( "WAD Files", &[EXT_WAD] )
+            .add_filter( "Maps", &[EXT_BSP] )
+        .pick_file()
         else
         {
             return;
         };
 
-        match read_texture_names( &file )
+        self.set_textures( app, &file );
+    }
+    /// Lists the lumps of a WAD, or the texture lump of a map, with their thumbnails to pick textures from.
+    /// Decoding the thumbnails of a large WAD takes a while, so it is done off the UI thread.
+    fn set_textures(&mut self, app: &MainWindow, path: &Path)
+    {
+        let from_map = path.has_extension( &[EXT_BSP] );
+        let filename = path
+            .file_name()
+            .map( |n| n.to_string_lossy().to_string() )
+        .unwrap_or_default();
+
+        self.texture_load += 1;
+        app.set_material_wad_button_text( format!( "Loading {filename}..." ).into() );
+
+        let ( load, path, app_weak ) = ( self.texture_load, path.to_path_buf(), app.as_weak() );
+
+        thread::spawn( move ||
         {
-            Ok( names ) =>
+            let textures =
+            if from_map
             {
-                self.wad_textures = names;
-                sync_wad_textures( &self.wad_textures, app );
+                map_textures( &path )
+            }
+            else
+            {
+                Wad::open( &path ).map( |wad| wad_textures( &wad ) )
+            };
 
-                let filename = file
-                    .file_name()
-                    .map( |n| n.to_string_lossy().to_string() )
-                .unwrap_or_default();
+            let _ = slint::invoke_from_event_loop( move ||
+            {
+                with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_textures_loaded( app, load, filename, from_map, textures ) );
+            });
+        });
+    }
+    /// Shows the textures read by `set_textures`, unless another WAD or map was picked since.
+    fn on_textures_loaded(&mut self, app: &MainWindow, load: u32, filename: String, from_map: bool, textures: io::Result<Vec<Listed>>)
+    {
+        if load != self.texture_load
+        {
+            return;
+        }
+        // The textures listed before stay when the new ones can't be read
+        if textures.is_ok()
+        {
+            self.textures_from = filename;
+        }
 
-                app.set_material_wad_button_text( format!( "Set WAD: {filename}" ).into() );
+        let button_text = if self.textures_from.is_empty() { "Set WAD".to_owned() } else { format!( "Set WAD: {}", self.textures_from ) };
+        app.set_material_wad_button_text( button_text.into() );
+
+        match textures
+        {
+            Ok( textures ) =>
+            {
+                self.textures = textures.into_iter().map( Listed::into_row ).collect();
+                app.set_material_wad_from_map( from_map );
+                app.set_material_wad_used_only( false );
+                self.sync_textures( app );
             }
 
             Err( e ) =>
             {
-                popup( "Error loading WAD", &format!( "Could not read texture names from WAD.\nReason: {e}" ),
+                let source = if from_map { "map" } else { "WAD" };
+                popup( "Error loading textures", &format!( "Could not read textures from {source}.\nReason: {e}" ),
                     MessageLevel::Error, MessageButtons::Ok );
             }
         }
     }
+    /// The textures shown in the list, leaving out the ones the map doesn't use when filtered to them.
+    fn listed(&self, app: &MainWindow) -> impl Iterator<Item = &WadTexture>
+    {
+        let used_only = app.get_material_wad_used_only();
+
+        self.textures
+            .iter()
+            .filter( move |( _, used )| !used_only || *used )
+        .map( |( texture, _ )| texture )
+    }
+
+    fn sync_textures(&self, app: &MainWindow)
+    {
+        let items: Vec<_> = self.listed( app ).cloned().collect();
+
+        app.set_material_wad_textures( ModelRc::from( items.as_slice() ) );
+        app.set_material_wad_current( -1 );
+    }
 
     fn on_wad_texture_selected(&mut self, app: &MainWindow, idx: i32)
     {
-        if idx < 0 || ( idx as usize ) >= self.wad_textures.len()
+        let Some( texture ) = usize::try_from( idx ).ok().and_then( |idx| self.listed( app ).nth( idx ) )
+        else
         {
             return;
-        }
+        };
 
         let kind = MaterialKind::index_to_kind( app.get_material_selected_kind() ).to_string();
-        let texture = self.wad_textures[ idx as usize ].clone();
+        let texture = texture.name.to_string();
 
         self.material_rows.push( ( texture, kind ) );
         sync_materials( &self.material_rows, app );
@@ -346,18 +512,20 @@ impl Controller
         if let Some( save_path ) = FileDialog::new().add_filter( "Save Materials file", &["txt"] ).save_file()
         {
             let filename = save_path.with_extension( "" ).to_string_lossy().to_string();
-            match MaterialEntry::to_file( &filename, &entries )
+            let report = MaterialEntry::to_file( &filename, &entries );
+
+            match &report.outcome
             {
-                Ok( () ) =>
+                Outcome::Failed( e ) =>
                 {
-                    popup( "Done", &format!( "Material file '{filename}.txt' created successfully." ), 
-                        MessageLevel::Info, MessageButtons::Ok );
+                    popup( "Error", &format!( "Failed to save material '{filename}.txt'.\nReason: {e}" ), 
+                        MessageLevel::Error, MessageButtons::Ok );
                 }
 
-                Err( e ) =>
+                _ =>
                 {
-                    popup( "Error", &format!( "Failed to save material '{filename}.txt'.\nReason: {e}" ), 
-                        MessageLevel::Error, MessageButtons::Ok );
+                    popup( "Done", &format!( "Material file '{filename}.txt' created successfully." ), 
+                        MessageLevel::Info, MessageButtons::Ok );
                 }
             }
         }
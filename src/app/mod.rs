@@ -15,12 +15,14 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
+mod auditgen;
 mod cfggen;
 mod materialsgen;
 mod replacegen;
 
 use std::
 {
+    io,
     path::Path,
     sync::mpsc,
     time::Duration,
@@ -60,7 +62,15 @@ use slint::
     }
 };
 
-use crate::prelude::*;
+use crate::
+{
+    lint::
+    {
+        Diagnostic,
+        Severity
+    },
+    prelude::*
+};
 /// Takes the controller out of the thread-local Cell, runs the body, puts it back.
 /// Use inside a Slint callback closure that captured `app_weak`.
 #[macro_export] macro_rules! with_controller
@@ -115,6 +125,61 @@ pub fn popup(title: &str, message: &str, level: MessageLevel, buttons: MessageBu
     rx.recv().unwrap_or( MessageDialogResult::Cancel )
 }
 
+/// Shows the outcome of a file operation, or the reason it could not run.
+pub fn show_report(kind: &str, result: io::Result<Report>)
+{
+    match result
+    {
+        Err( e ) =>
+        {
+            popup( &format!( "No {kind} files written" ), &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+        }
+
+        Ok( report ) if report.has_failures() =>
+        {
+            popup( "Finished with errors", &report.summary( kind ), MessageLevel::Warning, MessageButtons::Ok );
+        }
+
+        Ok( report ) if report.succeeded() == 0 =>
+        {
+            popup( &format!( "No {kind} files written" ), &report.summary( kind ), MessageLevel::Warning, MessageButtons::Ok );
+        }
+
+        Ok( report ) =>
+        {
+            popup( "Done", &report.summary( kind ), MessageLevel::Info, MessageButtons::Ok );
+        }
+    }
+}
+/// Joins lines for a dialog, cutting a long list short with a count of the lines left out so it still fits on screen.
+pub fn shown_lines(lines: &[String]) -> String
+{
+    const SHOWN: usize = 20;
+
+    let mut shown = lines[..lines.len().min( SHOWN )].to_vec();
+
+    if lines.len() > SHOWN
+    {
+        shown.push( format!( "...and {} more", lines.len() - SHOWN ) );
+    }
+
+    shown.join( "\n" )
+}
+/// Lists the lines of a replacement file that were skipped or look wrong.
+pub fn show_diagnostics(name: &str, diagnostics: &[Diagnostic])
+{
+    if diagnostics.is_empty()
+    {
+        return;
+    }
+
+    let lines: Vec<_> = diagnostics.iter().map( |d| format!( "{name}:{d}" ) ).collect();
+    let level = if diagnostics.iter().any( |d| d.severity == Severity::Error ) { MessageLevel::Error } else { MessageLevel::Warning };
+
+    popup( "Problems in replacement file", &format!( "{} line(s) of {name} have problems:\n\n{}", diagnostics.len(), shown_lines( &lines ) ),
+        level, MessageButtons::Ok );
+}
+
 pub fn load_cvar_presets(is_skill: bool) -> Vec<StandardListViewItem>
 {
     let mut cvars =
@@ -220,6 +285,7 @@ impl DragDropHandler
                     {
                         0 => app.set_cfg_is_dragging( true ),
                         2 => app.set_material_is_dragging( true ),
+                        3 => app.set_audit_is_dragging( true ),
                         _ => app.set_replace_is_dragging( true )
                     }
                 }
@@ -228,17 +294,20 @@ impl DragDropHandler
                     app.set_cfg_is_dragging( false );
                     app.set_replace_is_dragging( false );
                     app.set_material_is_dragging( false );
+                    app.set_audit_is_dragging( false );
                 }
                 else
                 {
                     app.set_cfg_is_dragging( false );
                     app.set_replace_is_dragging( false );
                     app.set_material_is_dragging( false );
+                    app.set_audit_is_dragging( false );
 
                     match current_tab
                     {
                         0 => app.invoke_cfg_dropped( path.into() ),
                         2 => app.invoke_material_dropped( path.into() ),
+                        3 => app.invoke_audit_dropped( path.into() ),
                         _ => app.invoke_dropped( path.into() )
                     }
                 }
@@ -280,6 +349,7 @@ pub fn launch_gui() -> Result<(), PlatformError>
     cfggen::Controller::new( &app ).register();
     replacegen::Controller::new( &app ).register();
     materialsgen::Controller::new( &app ).register();
+    auditgen::Controller::new( &app ).register();
 
     let app_weak = app.as_weak();
     // Position window in the centre of the screen
@@ -15,7 +15,16 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
-use std::cell::Cell;
+use std::
+{
+    cell::Cell,
+    collections::HashMap,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
 
 use rfd::
 {
@@ -28,9 +37,28 @@ use rfd::
 use slint::
 {
     ComponentHandle,
-    ModelRc
+    ModelRc,
+    SharedString
 };
 
+use crate::mdl::
+{
+    self,
+    EXT_MDL,
+    Mdl
+};
+use crate::spr::
+{
+    self,
+    EXT_SPR,
+    Spr
+};
+use crate::wav::
+{
+    self,
+    EXT_WAV,
+    Wav
+};
 use crate::prelude::*;
 use crate::with_controller;
 
@@ -38,14 +66,19 @@ use super::
 {
     MainWindow,
     make_row,
-    popup
+    popup,
+    show_diagnostics,
+    show_report,
+    shown_lines
 };
 
 #[derive( Default )]
 pub(crate) struct Controller
 {
     replace_rows: Vec<(String, String)>,
-    pending_from: Option<String>
+    pending_from: Option<String>,
+    /// Files picked or dropped for each game path, for reading their headers
+    sources: HashMap<String, PathBuf>
 }
 
 thread_local!
@@ -60,6 +93,7 @@ fn sync_ui(rows: &[(String, String)], app: &MainWindow)
     .collect();
 
     app.set_replacement_rows( ModelRc::from( items.as_slice() ) );
+    app.set_replace_details( SharedString::default() );
 }
 
 impl Controller
@@ -99,6 +133,36 @@ impl Controller
             with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_create_replacements( app ) );
         });
 
+        let app_weak = app.as_weak();
+        app.on_match_replacements( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_match_replacements( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_merge_replacements( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_merge_replacements( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_invert_replacements( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_invert_replacements( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_collapse_replacements( move ||
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_collapse_replacements( app ) );
+        });
+
+        let app_weak = app.as_weak();
+        app.on_replace_row_selected( move |row|
+        {
+            with_controller!( app_weak, CTRL, |ctrl, app| ctrl.on_row_selected( row, app ) );
+        });
+
         Self::default()
     }
 
@@ -106,6 +170,43 @@ impl Controller
     {
         CTRL.set( Some( self ) );
     }
+    /// The rows of the table as replacements, leaving out the ones replacing a file with itself
+    fn replacements(&self) -> Vec<Replacement>
+    {
+        let roots = GameRoots::from_config();
+
+        self.replace_rows
+            .iter()
+            .filter_map( |( from, to )| Replacement::try_new_in( &roots, from, to ).ok() )
+            .filter( |r| !r.is_redundant() )
+        .collect()
+    }
+
+    /// Adds a row for files picked on disk, remembering where they are. Returns what the new file lacks compared to the original.
+    fn add_row(&mut self, replacement: &Replacement, from: &Path, to: &Path) -> Vec<String>
+    {
+        self.replace_rows.push( ( replacement.get_original().to_string(), replacement.get_new().to_string() ) );
+        self.sources.insert( replacement.get_original().to_string(), from.to_path_buf() );
+        self.sources.insert( replacement.get_new().to_string(), to.to_path_buf() );
+
+        compatibility_warnings( from, to )
+    }
+    /// The file a game path refers to, the one it was added from or else the one the game would load
+    fn file_of(&self, game_path: &str, roots: &GameRoots) -> Option<PathBuf>
+    {
+        self.sources.get( game_path ).cloned().or_else( || roots.locate( game_path ) )
+    }
+
+    fn set_replacements(&mut self, replacements: &[Replacement], app: &MainWindow)
+    {
+        self.replace_rows = replacements
+            .iter()
+            .map( |r| ( r.get_original().to_string(), r.get_new().to_string() ) )
+        .collect();
+
+        app.set_replace_current_row( -1 );
+        sync_ui( &self.replace_rows, app );
+    }
     // ========== Resource Replacer Handlers ==========
     fn on_load_replacements(&mut self, app: &MainWindow)
     {
@@ -117,16 +218,27 @@ impl Controller
             return;
         };
 
-        let Some( replacements ) = Replacement::from_file( &file )
-        else
+        let roots = GameRoots::from_config();
+
+        let parsed =
+        match Replacement::from_file( &file, &roots )
         {
-            popup( "Error loading file",
-                "Could not load the replacement file. Check that the file exists and has valid entries.",
-                MessageLevel::Error, MessageButtons::Ok );
+            Ok( parsed ) => parsed,
+            Err( e ) =>
+            {
+                popup( "Error loading file", &format!( "Could not load the replacement file:\n{e}" ),
+                    MessageLevel::Error, MessageButtons::Ok );
 
-            return;
+                return;
+            }
         };
 
+        let name = file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        show_diagnostics( &name, &parsed.diagnostics );
+        show_sound_warnings( &name, &wav::check_replacements( &parsed.replacements, &roots ) );
+
+        let replacements = parsed.replacements;
+
         if replacements.is_empty()
         {
             popup( "No replacements",
@@ -169,6 +281,12 @@ impl Controller
         {
             None =>// Nothing was dragged here before, this is the first time i.e original
             {
+                if let Err( e ) = GameRoots::from_config().resolve( path )
+                {
+                    popup( "Invalid file", &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+                    return;
+                }
+
                 let path = path.to_string();
                 let confirm = 
                 popup( "File stored", &format!( "Now drop the replacement file for:\n{path}" ),
@@ -192,19 +310,19 @@ impl Controller
                     return;
                 }
 
-                match ( Replacement::classify( &from ), Replacement::classify( path ) )
+                match Replacement::try_new_in( &GameRoots::from_config(), &from, path )
                 {
-                    ( Some( Replacement::Models { .. } ), Some( Replacement::Models { .. } ) ) |
-                    ( Some( Replacement::Sounds { .. } ), Some( Replacement::Sounds { .. } ) ) =>
+                    Ok( r ) =>
                     {
-                        self.replace_rows.push( ( Replacement::truncate_path( &from ).to_string(), Replacement::truncate_path( path ).to_string() ) );
+                        let warnings = self.add_row( &r, Path::new( &from ), Path::new( path ) );
                         sync_ui( &self.replace_rows, app );
+                        warn_incompatible( Path::new( &from ), Path::new( path ), &warnings );
                     }
 
-                    _ =>
+                    Err( e ) =>
                     {
-                        popup( "Type mismatch", 
-                            "'From' and 'To' must both be models or both be sounds.", 
+                        popup( "Invalid replacement",
+                            &format!( "{e}\n'From' and 'To' must both be models or both be sounds, inside the game's content folders." ),
                             MessageLevel::Warning, MessageButtons::Ok );
 
                         self.pending_from = Some( from );
@@ -228,6 +346,14 @@ impl Controller
             Some( path ) => path.to_string_lossy().into_owned(),
             None => return
         };
+
+        let roots = GameRoots::from_config();
+
+        if let Err( e ) = roots.resolve( &from )
+        {
+            popup( "Invalid file", &e.to_string(), MessageLevel::Warning, MessageButtons::Ok );
+            return;
+        }
         // Constrain "to" file type towards the "from" file type
         let extensions = 
         if let Some( types ) = Replacement::classify( &from )
@@ -272,31 +398,21 @@ impl Controller
                 MessageButtons::Ok );
         };
 
-        if Replacement::classify( &to ).is_none()
+        match Replacement::try_new_in( &roots, &from, &to )
         {
-            popup( "Invalid file", 
-                "The selected file does not have a recognized model or sound extension.",
-                MessageLevel::Warning, MessageButtons::Ok );
-
-            return;
-        }
-
-        match ( Replacement::classify( &from ), Replacement::classify( &to ) )
-        {
-            ( Some( Replacement::Models { .. } ), Some( Replacement::Models { .. } ) ) |
-            ( Some( Replacement::Sounds { .. } ), Some( Replacement::Sounds { .. } ) ) =>
+            Ok( r ) =>
             {
-                self.replace_rows.push( ( Replacement::truncate_path( &from ).to_string(), Replacement::truncate_path( &to ).to_string() ) );
+                let warnings = self.add_row( &r, Path::new( &from ), Path::new( &to ) );
                 sync_ui( &self.replace_rows, app );
+                warn_incompatible( Path::new( &from ), Path::new( &to ), &warnings );
             }
 
-            _ =>
+            Err( e ) =>
             {
-                popup( "Type mismatch", "'From' and 'To' must both be models or both be sounds.", 
+                popup( "Invalid replacement", &format!( "{e}\n'From' and 'To' must both be models or both be sounds, inside the game's content folders." ),
                     MessageLevel::Warning, 
                     MessageButtons::Ok );
             }
-            
         }
     }
     /// Removes a replacement entry
@@ -329,64 +445,294 @@ impl Controller
         }
     }
 
-    fn on_create_replacements(&self, _app: &MainWindow)
+    /// Pairs up the models and sounds of two folders and adds the pairs to the list for review
+    fn on_match_replacements(&mut self, app: &MainWindow)
     {
-        if self.replace_rows.is_empty()
+        let Some( from_root ) = FileDialog::new().set_title( "Select folder with the original files" ).pick_folder()
+        else
         {
-            popup( "No replacements", 
-                "Add at least one replacement pair.", 
+            return;
+        };
+
+        let Some( to_root ) = FileDialog::new().set_title( "Select folder with the replacement files" ).pick_folder()
+        else
+        {
+            return;
+        };
+
+        if from_root == to_root
+        {
+            popup( "Same folder", "The original and replacement folders must be different.",
                 MessageLevel::Warning, MessageButtons::Ok );
+
             return;
         }
 
-        let mut replacements: Vec<_> = vec![];
-        for ( from, to ) in &self.replace_rows
+        let rules = MatchRules
         {
-            if let Some( r ) = Replacement::try_new( from, to ) && !r.is_redundant()
+            ignore_suffixes: MatchRules::parse_suffixes( &app.get_replace_match_suffixes() ),
+            by_name: app.get_replace_match_by_name()
+        };
+
+        let matched = Replacement::match_dirs( &from_root, &to_root, &rules, &GameRoots::from_config() );
+        let mut added = 0;
+        let mut warnings = vec![];
+
+        for ( r, ( from, to ) ) in matched.replacements.iter().zip( &matched.files )
+        {
+            if !self.replace_rows.iter().any( |( original, _ )| original == r.get_original() )
             {
-                replacements.push( r );
+                warnings.extend( self.add_row( r, from, to ).into_iter().map( |w| format!( "{}: {w}", r.get_new() ) ) );
+                added += 1;
             }
         }
 
-        let ( models, sounds ) = Replacement::partition_replacements( &replacements );
+        sync_ui( &self.replace_rows, app );
+
+        let mut message = format!( "Matched {} file(s), {added} added to the list.", matched.replacements.len() );
+
+        let mut list = |lines: &[String], heading: &str|
+        {
+            if !lines.is_empty()
+            {
+                message.push_str( &format!( "\n\n{} {heading}:\n{}", lines.len(), shown_lines( lines ) ) );
+            }
+        };
+
+        list( &warnings, "warning(s), the new files may break maps that use the originals" );
+        list( &matched.same, "file(s) have the same game path in both folders and were left out" );
+        list( &matched.unmatched, "file(s) had no match" );
+        list( &matched.rejected, "pair(s) could not be used" );
+
+        popup( "Match folders", &message, MessageLevel::Info, MessageButtons::Ok );
+    }
+
+    /// Shows the header details of the selected row's files, and how they differ
+    fn on_row_selected(&self, row: i32, app: &MainWindow)
+    {
+        let Some( ( from, to ) ) = usize::try_from( row ).ok().and_then( |row| self.replace_rows.get( row ) )
+        else
+        {
+            app.set_replace_details( SharedString::default() );
+            return;
+        };
+
+        let roots = GameRoots::from_config();
+        let ( from_file, to_file ) = ( self.file_of( from, &roots ), self.file_of( to, &roots ) );
+
+        let line = |label: &str, path: &str, file: &Option<PathBuf>|
+        match file
+        {
+            Some( file ) => format!( "{label}: {path}: {}", describe( file ) ),
+            None => format!( "{label}: {path}: file not found" )
+        };
+
+        let mut lines = vec![line( "From", from, &from_file ), line( "To", to, &to_file )];
+
+        if let ( Some( from_file ), Some( to_file ) ) = ( &from_file, &to_file )
+        {
+            lines.extend( compatibility_warnings( from_file, to_file ).into_iter().map( |w| format!( "Warning: {w}" ) ) );
+        }
+
+        app.set_replace_details( lines.join( "\n" ).into() );
+    }
+    /// Merges .gmr/.gsr files into the table. Entries already in the table win over the files.
+    fn on_merge_replacements(&mut self, app: &MainWindow)
+    {
+        let Some( files ) = FileDialog::new()
+            .set_title( "Select replacement files to merge" )
+            .add_filter( "Replacements", &[EXT_GMR, EXT_GSR] )
+        .pick_files()
+        else
+        {
+            return;
+        };
+
+        let mut sets = vec![( "table".to_string(), self.replacements() )];
+        let roots = GameRoots::from_config();
+
+        for file in &files
+        {
+            let name = file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+            match Replacement::from_file( file, &roots )
+            {
+                Ok( parsed ) =>
+                {
+                    show_diagnostics( &name, &parsed.diagnostics );
+                    show_sound_warnings( &name, &wav::check_replacements( &parsed.replacements, &roots ) );
+                    sets.push( ( name, parsed.replacements ) );
+                }
+
+                Err( e ) =>
+                {
+                    popup( "Error loading file", &format!( "Could not load '{name}', it will be left out of the merge:\n{e}" ),
+                        MessageLevel::Error, MessageButtons::Ok );
+                }
+            }
+        }
+
+        let ( merged, conflicts ) = Replacement::merge( sets.iter().map( |( name, r )| ( name.as_str(), r.as_slice() ) ) );
+        self.set_replacements( &merged, app );
+
+        if !conflicts.is_empty()
+        {
+            show_conflicts( "Merge conflicts", "was given different files, the first one was kept", &conflicts );
+        }
+    }
+    /// Swaps the original and new file of every row, to undo a replacement
+    fn on_invert_replacements(&mut self, app: &MainWindow)
+    {
+        let replacements = self.replacements();
+
+        if replacements.is_empty()
+        {
+            return;
+        }
+
+        let ( inverted, conflicts ) = Replacement::invert( &replacements );
+        self.set_replacements( &inverted, app );
+
+        if !conflicts.is_empty()
+        {
+            show_conflicts( "Invert conflicts", "was the new file of several replacements, only the first one was inverted", &conflicts );
+        }
+    }
+    /// Points every row at the end of its chain of replacements
+    fn on_collapse_replacements(&mut self, app: &MainWindow)
+    {
+        let replacements = self.replacements();
+
+        if replacements.is_empty()
+        {
+            return;
+        }
+
+        let ( collapsed, cycles ) = Replacement::collapse( &replacements );
+        self.set_replacements( &collapsed, app );
+
+        if !cycles.is_empty()
+        {
+            let cycles: Vec<_> = cycles.iter().map( |c| format!( "{} -> {}", c.join( " -> " ), c[0] ) ).collect();
+
+            popup( "Replacement cycles",
+                &format!( "These replacements go round in a cycle, so they were left as they are:\n\n{}", cycles.join( "\n" ) ),
+                MessageLevel::Warning, MessageButtons::Ok );
+        }
+    }
+
+    fn on_create_replacements(&self, _app: &MainWindow)
+    {
+        if self.replace_rows.is_empty()
+        {
+            popup( "No replacements", 
+                "Add at least one replacement pair.", 
+                MessageLevel::Warning, MessageButtons::Ok );
+            return;
+        }
+
+        let ( models, sounds ) = Replacement::partition_replacements( &self.replacements() );
 
         if models.is_empty() && sounds.is_empty()
         {
             return;
         }
 
-        let mut errors = vec![];
+        let mut report = Report::default();
 
         if !models.is_empty() 
         && let Some( save_path ) = FileDialog::new().add_filter( "Save as GMR", &[EXT_GMR] ).save_file()
         {
             let gmr_filename = save_path.with_extension( "" ).to_string_lossy().to_string();
-            if let Err( e ) = Replacement::to_file( &gmr_filename, &models )
-            { 
-                errors.push( format!( "Model: {e}" ) );
-            }
+            report.push( Replacement::to_file( &gmr_filename, &models ) );
         }
 
         if !sounds.is_empty() 
         && let Some( save_path ) = FileDialog::new().add_filter( "Save as GSR", &[EXT_GSR] ).save_file()
         {
             let gsr_filename = save_path.with_extension( "" ).to_string_lossy().to_string();
-            if let Err( e ) = Replacement::to_file( &gsr_filename, &sounds )
-            { 
-                errors.push( format!( "Sound: {e}" ) );
-            }
+            report.push( Replacement::to_file( &gsr_filename, &sounds ) );
         }
 
-        let ( title, msg, level ) =
-        if errors.is_empty()
+        if report.files.is_empty()
         {
-            ( "Done", "Replacements created successfully.".to_owned(), MessageLevel::Info )
+            return;
         }
-        else
-        {
-            ( "Error", format!( "Failed:\n{}", errors.join( "\n" ) ), MessageLevel::Error )
-        };
 
-        popup( title, &msg, level, MessageButtons::Ok );
+        show_report( "replacement", Ok( report ) );
+    }
+}
+
+fn show_conflicts(title: &str, problem: &str, conflicts: &[Conflict])
+{
+    let conflicts: Vec<_> = conflicts.iter().map( Conflict::to_string ).collect();
+
+    popup( title, &format!( "Each of these {problem}:\n\n{}", conflicts.join( "\n" ) ),
+        MessageLevel::Warning, MessageButtons::Ok );
+}
+/// Header details of a model or sprite
+fn describe(file: &Path) -> String
+{
+    let details =
+    if file.has_extension( &[EXT_MDL] )
+    {
+        Mdl::read( file ).map( |m| m.to_string() )
     }
+    else if file.has_extension( &[EXT_SPR] )
+    {
+        Spr::read( file ).map( |s| s.to_string() )
+    }
+    else if file.has_extension( &[EXT_WAV] )
+    {
+        Wav::read( file ).map( |w| w.to_string() )
+    }
+    else
+    {
+        return wav::unplayable( file ).unwrap_or_else( || "no details".into() );
+    };
+
+    details.unwrap_or_else( |e| e.to_string() )
+}
+/// What a replacement model or sprite lacks compared to the one it replaces, or why a replacement sound won't play right
+fn compatibility_warnings(from: &Path, to: &Path) -> Vec<String>
+{
+    if from.has_extension( &[EXT_MDL] ) && to.has_extension( &[EXT_MDL] )
+    {
+        mdl::replacement_warnings( from, to )
+    }
+    else if from.has_extension( &[EXT_SPR] ) && to.has_extension( &[EXT_SPR] )
+    {
+        spr::replacement_warnings( from, to )
+    }
+    else if to.has_extension( &EXTS_SOUNDS )
+    {
+        wav::replacement_warnings( from, to )
+    }
+    else
+    {
+        vec![]
+    }
+}
+/// Warns about what a replacement lacks compared to the one it replaces
+fn warn_incompatible(from: &Path, to: &Path, warnings: &[String])
+{
+    if warnings.is_empty()
+    {
+        return;
+    }
+
+    popup( "Replacement may not be compatible",
+        &format!( "'{}' may break maps that use '{}':\n\n{}", to.display(), from.display(), warnings.join( "\n" ) ),
+        MessageLevel::Warning, MessageButtons::Ok );
+}
+/// Lists the sounds of a replacement file that won't play, or won't play like the sounds they replace
+fn show_sound_warnings(name: &str, warnings: &[String])
+{
+    if warnings.is_empty()
+    {
+        return;
+    }
+
+    popup( "Problems with replacement sounds", &format!( "{} problem(s) with the sounds in {name}:\n\n{}", warnings.len(), shown_lines( warnings ) ),
+        MessageLevel::Warning, MessageButtons::Ok );
 }
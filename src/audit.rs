@@ -0,0 +1,257 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    collections::
+    {
+        HashMap,
+        HashSet
+    },
+    fs,
+    io,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+use serde::Serialize;
+
+use crate::
+{
+    bsp,
+    cfgdoc::CfgDocument,
+    cvar::load_bsps
+};
+/// CVars that point a map at another file.
+const REFERENCE_CVARS: [&str; 3] = ["globalmodellist", "globalsoundlist", "materials_file"];
+/// Suffixes of the files that belong to a BSP, the longest first so `_skl.cfg` is not taken for `.cfg`.
+pub(crate) const COMPANION_SUFFIXES: [&str; 4] = ["_skl.cfg", "_motd.txt", ".cfg", ".res"];
+/// A file named by a CVar in a map's cfg.
+#[derive( Clone, Debug, Serialize )]
+pub struct Reference
+{
+    pub cfg: String,
+    pub cvar: String,
+    pub path: String,
+    pub found: bool
+}
+/// Which companion files a BSP has.
+#[derive( Clone, Debug, Serialize )]
+pub struct MapAudit
+{
+    pub bsp: String,
+    pub cfg: bool,
+    pub skill_cfg: bool,
+    pub motd: bool,
+    pub res: bool,
+    pub references: Vec<Reference>,
+    /// Why the BSP can't be read as a map, if it can't.
+    #[serde( skip_serializing_if = "Option::is_none" )]
+    pub error: Option<String>
+}
+
+impl MapAudit
+{
+    pub fn missing_references(&self) -> impl Iterator<Item = &Reference>
+    {
+        self.references.iter().filter( |r| !r.found )
+    }
+}
+/// Companion file report for a maps folder.
+#[derive( Clone, Debug, Default, Serialize )]
+pub struct Audit
+{
+    pub maps_dir: PathBuf,
+    pub maps: Vec<MapAudit>,
+    /// Companion files whose BSP is not in the folder.
+    pub orphans: Vec<String>
+}
+
+impl Audit
+{
+    pub fn run(maps_dir: &Path) -> io::Result<Self>
+    {
+        let files: Vec<String> = fs::read_dir( maps_dir )?
+            .filter_map( Result::ok )
+            .filter( |e| e.file_type().is_ok_and( |t| t.is_file() ) )
+            .map( |e| e.file_name().to_string_lossy().into_owned() )
+        .collect();
+        // Files are matched case-insensitively, same as the game does on Windows
+        let lower_files: HashMap<String, &str> = files.iter().map( |f| ( f.to_ascii_lowercase(), f.as_str() ) ).collect();
+
+        let mut bsps: Vec<(String, PathBuf)> = load_bsps( maps_dir )
+            .into_iter()
+            .filter_map( |p| Some( ( p.file_stem()?.to_string_lossy().into_owned(), p ) ) )
+        .collect();
+
+        bsps.sort_by_key( |( b, _ )| b.to_ascii_lowercase() );
+
+        let maps = bsps
+            .iter()
+            .map( |( bsp, path )|
+            {
+                let find = |suffix: &str| lower_files.get( &format!( "{bsp}{suffix}" ).to_ascii_lowercase() ).copied();
+                let has = |suffix: &str| find( suffix ).is_some();
+
+                let references = [".cfg", "_skl.cfg"]
+                    .iter()
+                    .filter_map( |suffix| find( suffix ) )
+                    .flat_map( |cfg| references( maps_dir, bsp, cfg ) )
+                .collect();
+
+                MapAudit
+                {
+                    bsp: bsp.clone(),
+                    cfg: has( ".cfg" ),
+                    skill_cfg: has( "_skl.cfg" ),
+                    motd: has( "_motd.txt" ),
+                    res: has( ".res" ),
+                    references,
+                    error: bsp::read_entities( path ).err().map( |e| e.to_string() )
+                }
+            })
+        .collect();
+
+        let bsp_stems: HashSet<String> = bsps.iter().map( |( b, _ )| b.to_ascii_lowercase() ).collect();
+
+        let mut orphans: Vec<String> = files
+            .into_iter()
+            .filter( |f|
+            {
+                let stems = companion_stems( &f.to_ascii_lowercase() );
+                !stems.is_empty() && !stems.iter().any( |s| bsp_stems.contains( s ) )
+            })
+        .collect();
+
+        orphans.sort_by_key( |o| o.to_ascii_lowercase() );
+
+        Ok( Self { maps_dir: maps_dir.to_path_buf(), maps, orphans } )
+    }
+
+    pub fn missing_references(&self) -> impl Iterator<Item = &Reference>
+    {
+        self.maps.iter().flat_map( MapAudit::missing_references )
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String>
+    {
+        serde_json::to_string_pretty( self )
+    }
+
+    pub fn to_markdown(&self) -> String
+    {
+        let mark = |b: bool| if b { "yes" } else { "no" };
+
+        let mut out = format!( "# Map audit: {}\n\n", self.maps_dir.display() );
+        out.push_str( "| BSP | CFG | Skill CFG | MOTD | RES | Missing files |\n" );
+        out.push_str( "|-----|-----|-----------|------|-----|---------------|\n" );
+
+        for map in &self.maps
+        {
+            let missing: Vec<_> = map.missing_references().map( |r| r.path.as_str() ).collect();
+
+            out.push_str( &format!( "| {} | {} | {} | {} | {} | {} |\n",
+                map.bsp, mark( map.cfg ), mark( map.skill_cfg ), mark( map.motd ), mark( map.res ), missing.join( ", " ) ) );
+        }
+
+        let invalid: Vec<_> = self.maps.iter().filter_map( |m| Some( ( &m.bsp, m.error.as_ref()? ) ) ).collect();
+
+        if !invalid.is_empty()
+        {
+            out.push_str( "\n## Invalid BSPs\n\n" );
+
+            for ( bsp, error ) in invalid
+            {
+                out.push_str( &format!( "- `{bsp}.bsp`: {error}\n" ) );
+            }
+        }
+
+        let missing: Vec<_> = self.missing_references().collect();
+
+        if !missing.is_empty()
+        {
+            out.push_str( "\n## Missing referenced files\n\n" );
+
+            for r in missing
+            {
+                out.push_str( &format!( "- `{}`: `{} {}`\n", r.cfg, r.cvar, r.path ) );
+            }
+        }
+
+        if !self.orphans.is_empty()
+        {
+            out.push_str( "\n## Orphaned files\n\n" );
+
+            for orphan in &self.orphans
+            {
+                out.push_str( &format!( "- `{orphan}`\n" ) );
+            }
+        }
+
+        out
+    }
+}
+/// Gets the BSP names a file could belong to, going by its suffix. Empty if it is not a companion file.
+fn companion_stems(name: &str) -> Vec<String>
+{
+    COMPANION_SUFFIXES
+        .iter()
+        .filter_map( |suffix| name.strip_suffix( suffix ) )
+        .filter( |stem| !stem.is_empty() )
+        .map( str::to_owned )
+    .collect()
+}
+/// Lists the files a cfg points to, and whether they can be found.
+fn references(maps_dir: &Path, bsp: &str, cfg: &str) -> Vec<Reference>
+{
+    let Ok( doc ) = CfgDocument::from_file( &maps_dir.join( cfg ) )
+    else
+    {
+        return vec![];
+    };
+
+    doc
+        .cvars()
+        .filter( |c| !c.value.is_empty() && REFERENCE_CVARS.iter().any( |r| c.is_named( r ) ) )
+        .map( |c| Reference
+        {
+            cfg: cfg.to_owned(),
+            cvar: c.name.clone(),
+            path: c.value.clone(),
+            found: reference_candidates( maps_dir, bsp, &c.name, &c.value ).iter().any( |p| p.is_file() )
+        })
+    .collect()
+}
+/// Places the game looks for a referenced file: next to the BSP, in the game folder,
+/// or in the map's own models/sound folder for replacement and materials files.
+fn reference_candidates(maps_dir: &Path, bsp: &str, cvar: &str, value: &str) -> Vec<PathBuf>
+{
+    let mut candidates = vec![maps_dir.join( value )];
+
+    if let Some( game_dir ) = maps_dir.parent()
+    {
+        candidates.push( game_dir.join( value ) );
+
+        let folder = if cvar.eq_ignore_ascii_case( "globalmodellist" ) { "models" } else { "sound" };
+        candidates.push( game_dir.join( folder ).join( bsp ).join( value ) );
+    }
+
+    candidates
+}
@@ -0,0 +1,263 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fs,
+    io,
+    path::
+    {
+        Path,
+        PathBuf
+    },
+    time::
+    {
+        SystemTime,
+        UNIX_EPOCH
+    }
+};
+
+use serde::
+{
+    Deserialize,
+    Serialize
+};
+
+use crate::
+{
+    config::
+    {
+        self,
+        Config
+    },
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
+    utils::write_atomic
+};
+
+const BACKUP_DIR: &str = "backups";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Backup sets kept when the config does not set a limit.
+pub const DEFAULT_BACKUP_LIMIT: usize = 20;
+/// A file as it was before an operation touched it.
+#[derive( Clone, Debug, Serialize, Deserialize )]
+pub struct BackupEntry
+{
+    pub original: PathBuf,
+    /// Name of the copy in the backup set, `None` if the file did not exist yet.
+    pub backup: Option<String>
+}
+/// What is stored alongside the copied files in a backup set.
+#[derive( Clone, Debug, Serialize, Deserialize )]
+pub struct Manifest
+{
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub operation: String,
+    pub files: Vec<BackupEntry>
+}
+/// Snapshot of the files touched by one operation.
+#[derive( Clone, Debug )]
+pub struct BackupSet
+{
+    pub dir: PathBuf,
+    pub manifest: Manifest
+}
+
+impl BackupSet
+{
+    fn read(dir: &Path) -> io::Result<Self>
+    {
+        let manifest = fs::read_to_string( dir.join( MANIFEST_FILE ) )?;
+        let manifest = serde_json::from_str( &manifest ).map_err( io::Error::other )?;
+
+        Ok( Self { dir: dir.to_path_buf(), manifest } )
+    }
+    /// Puts every file back the way it was, deleting the ones that did not exist before.
+    pub fn restore(&self) -> Report
+    {
+        self.manifest.files
+            .iter()
+            .map( |entry|
+            {
+                let result =
+                match &entry.backup
+                {
+                    Some( backup ) => fs::read( self.dir.join( backup ) )
+                        .and_then( |content| write_atomic( &entry.original, content ) )
+                    .map( |()| Outcome::Written ),
+                    // Sets from older versions may hold relative paths, which would depend on the current folder
+                    None if entry.original.is_relative() => Ok( Outcome::Skipped( "Recorded without its full path, not deleting it".into() ) ),
+                    None if entry.original.exists() => fs::remove_file( &entry.original ).map( |_| Outcome::Deleted ),
+                    None => Ok( Outcome::Skipped( "Did not exist before, nothing to restore".into() ) )
+                };
+
+                FileReport::from_result( entry.original.clone(), result )
+            })
+        .collect()
+    }
+    /// Removes the set, for an operation that ended up changing nothing.
+    pub fn discard(self) -> io::Result<()>
+    {
+        fs::remove_dir_all( &self.dir )
+    }
+}
+/// Full path of a file that may not exist yet, so restoring it does not depend on the folder the app runs from.
+fn absolute(path: &Path) -> io::Result<PathBuf>
+{
+    if let Ok( path ) = path.canonicalize()
+    {
+        return Ok( path );
+    }
+
+    let name = path.file_name().ok_or_else( || io::Error::new( io::ErrorKind::InvalidInput, format!( "{} is not a file.", path.display() ) ) )?;
+    let parent = path.parent().filter( |p| !p.as_os_str().is_empty() ).unwrap_or( Path::new( "." ) );
+
+    Ok( parent.canonicalize().or_else( |_| std::path::absolute( parent ) )?.join( name ) )
+}
+/// Folder of timestamped backup sets, the newest set is the last operation.
+pub struct BackupStore
+{
+    root: PathBuf,
+    limit: usize
+}
+
+impl BackupStore
+{
+    pub fn new(root: PathBuf, limit: usize) -> Self
+    {
+        Self { root, limit: limit.max( 1 ) }
+    }
+    /// The store in the app data folder, keeping as many sets as the config allows.
+    pub fn open() -> Self
+    {
+        let limit = Config::get().backup_limit.unwrap_or( DEFAULT_BACKUP_LIMIT );
+        Self::new( config::appdata_base().join( BACKUP_DIR ), limit )
+    }
+    /// Copies the files into a new backup set before `operation` changes them.
+    /// Hand the set to `keep` once the operation is done, or `BackupSet::discard` it if the operation failed.
+    pub fn snapshot(&self, operation: &str, paths: &[PathBuf]) -> io::Result<BackupSet>
+    {
+        let now = SystemTime::now().duration_since( UNIX_EPOCH ).unwrap_or_default();
+        let mut dir = self.root.join( now.as_millis().to_string() );
+        // Two operations in the same millisecond get their own sets
+        for n in 1..
+        {
+            if !dir.exists()
+            {
+                break;
+            }
+
+            dir = self.root.join( format!( "{}_{n}", now.as_millis() ) );
+        }
+
+        fs::create_dir_all( &dir )?;
+
+        let mut files = vec![];
+
+        for ( i, path ) in paths.iter().enumerate()
+        {
+            let backup =
+            if path.is_file()
+            {
+                let name = format!( "{i}_{}", path.file_name().unwrap_or_default().to_string_lossy() );
+                fs::copy( path, dir.join( &name ) )?;
+
+                Some( name )
+            }
+            else
+            {
+                None
+            };
+
+            files.push( BackupEntry { original: absolute( path )?, backup } );
+        }
+
+        let manifest = Manifest { created: now.as_secs(), operation: operation.to_owned(), files };
+        write_atomic( &dir.join( MANIFEST_FILE ), serde_json::to_string_pretty( &manifest ).map_err( io::Error::other )? )?;
+
+        Ok( BackupSet { dir, manifest } )
+    }
+    /// Keeps the set if the operation changed any file, then drops the oldest sets over the limit.
+    /// The set of an operation that changed nothing is removed, so `restore_last` goes to the one before it.
+    pub fn keep(&self, set: BackupSet, report: &Report) -> io::Result<()>
+    {
+        if report.succeeded() == 0
+        {
+            return set.discard();
+        }
+
+        self.prune()
+    }
+    /// Lists the backup sets, oldest first.
+    pub fn sets(&self) -> io::Result<Vec<BackupSet>>
+    {
+        if !self.root.try_exists()?
+        {
+            return Ok( vec![] );
+        }
+
+        let mut sets: Vec<_> = fs::read_dir( &self.root )?
+            .filter_map( Result::ok )
+            .filter( |e| e.path().is_dir() )
+            .filter_map( |e| BackupSet::read( &e.path() ).ok() )
+        .collect();
+
+        sets.sort_by( |a, b| a.manifest.created.cmp( &b.manifest.created ).then_with( || a.dir.cmp( &b.dir ) ) );
+        Ok( sets )
+    }
+    /// Gets the backup set of the last operation.
+    pub fn last(&self) -> io::Result<Option<BackupSet>>
+    {
+        Ok( self.sets()?.pop() )
+    }
+    /// Undoes the last operation and removes its backup set, so the one before it is next.
+    pub fn restore_last(&self) -> io::Result<Report>
+    {
+        let Some( set ) = self.last()?
+        else
+        {
+            return Err( io::Error::new( io::ErrorKind::NotFound, "There are no backups to restore." ) );
+        };
+
+        let report = set.restore();
+
+        if !report.has_failures()
+        {
+            fs::remove_dir_all( &set.dir )?;
+        }
+
+        Ok( report )
+    }
+
+    fn prune(&self) -> io::Result<()>
+    {
+        let sets = self.sets()?;
+        let excess = sets.len().saturating_sub( self.limit );
+
+        for set in &sets[..excess]
+        {
+            fs::remove_dir_all( &set.dir )?;
+        }
+
+        Ok( () )
+    }
+}
@@ -0,0 +1,440 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fs::
+    {
+        self,
+        File
+    },
+    io::
+    {
+        self,
+        Read,
+        Seek,
+        SeekFrom
+    },
+    path::Path
+};
+
+use strum::
+{
+    Display,
+    EnumIter,
+    IntoEnumIterator
+};
+
+use crate::wad::
+{
+    Image,
+    decode_miptex
+};
+
+pub const BSP_VERSION: i32 = 30;
+pub const LUMP_COUNT: usize = 15;
+const HEADER_SIZE: usize = 4 + LUMP_COUNT * 8;
+const TEXINFO_SIZE: usize = 40;
+const FACE_SIZE: usize = 20;
+
+#[repr( usize )]
+#[derive( Clone, Copy, Debug, PartialEq, Display, EnumIter )]
+pub enum Lump
+{
+    Entities,
+    Planes,
+    Textures,
+    Vertices,
+    Visibility,
+    Nodes,
+    TexInfo,
+    Faces,
+    Lighting,
+    ClipNodes,
+    Leaves,
+    MarkSurfaces,
+    Edges,
+    SurfEdges,
+    Models
+}
+/// Where a lump sits in the file.
+#[derive( Clone, Copy, Debug, Default, PartialEq )]
+pub struct LumpInfo
+{
+    pub offset: u32,
+    pub length: u32
+}
+
+impl LumpInfo
+{
+    fn range(&self) -> std::ops::Range<usize>
+    {
+        self.offset as usize..self.offset as usize + self.length as usize
+    }
+}
+/// Version and lump directory at the start of a BSP file.
+#[derive( Clone, Debug )]
+pub struct BspHeader
+{
+    pub version: i32,
+    pub lumps: [LumpInfo; LUMP_COUNT],
+    /// Blue Shift maps store the planes first and the entities second.
+    pub swapped: bool
+}
+
+impl BspHeader
+{
+    fn parse(bytes: &[u8]) -> io::Result<Self>
+    {
+        if bytes.len() < HEADER_SIZE
+        {
+            return Err( invalid( "File is too small to be a BSP." ) );
+        }
+
+        let read_i32 = |at: usize| i32::from_le_bytes( [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]] );
+        let version = read_i32( 0 );
+
+        if version != BSP_VERSION
+        {
+            return Err( invalid( &format!( "Unsupported BSP version {version}, expected {BSP_VERSION}." ) ) );
+        }
+
+        let mut lumps = [LumpInfo::default(); LUMP_COUNT];
+
+        for ( ( i, info ), lump ) in lumps.iter_mut().enumerate().zip( Lump::iter() )
+        {
+            let ( offset, length ) = ( read_i32( 4 + i * 8 ), read_i32( 8 + i * 8 ) );
+
+            if offset < 0 || length < 0
+            {
+                return Err( invalid( &format!( "The {lump} lump has a negative offset or length." ) ) );
+            }
+
+            *info = LumpInfo { offset: offset as u32, length: length as u32 };
+        }
+
+        Ok( Self { version, lumps, swapped: false } )
+    }
+    /// Reads and checks the header without loading the rest of the file.
+    pub fn read(path: &Path) -> io::Result<Self>
+    {
+        let mut file = File::open( path )?;
+        let file_len = file.metadata()?.len();
+
+        let mut bytes = [0u8; HEADER_SIZE];
+        file.read_exact( &mut bytes ).map_err( |_| invalid( "File is too small to be a BSP." ) )?;
+
+        let mut header = Self::parse( &bytes )?;
+        header.check_bounds( file_len )?;
+
+        let mut first = [0u8];
+        file.seek( SeekFrom::Start( header.lumps[0].offset as u64 ) )?;
+        let first = if file.read( &mut first )? == 0 { None } else { Some( first[0] ) };
+        header.detect_swap( first );
+
+        Ok( header )
+    }
+
+    /// Reads and checks the header of a BSP already in memory.
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self>
+    {
+        let mut header = Self::parse( data )?;
+        header.check_bounds( data.len() as u64 )?;
+        header.detect_swap( data.get( header.lumps[0].offset as usize ).copied() );
+
+        Ok( header )
+    }
+
+    fn check_bounds(&self, file_len: u64) -> io::Result<()>
+    {
+        for ( info, lump ) in self.lumps.iter().zip( Lump::iter() )
+        {
+            if info.offset as u64 + info.length as u64 > file_len
+            {
+                return Err( invalid( &format!( "The {lump} lump runs past the end of the file." ) ) );
+            }
+        }
+
+        Ok( () )
+    }
+
+    pub fn lump(&self, lump: Lump) -> LumpInfo
+    {
+        self.lumps[self.index( lump )]
+    }
+
+    fn index(&self, lump: Lump) -> usize
+    {
+        match lump
+        {
+            Lump::Entities if self.swapped => Lump::Planes as usize,
+            Lump::Planes if self.swapped => Lump::Entities as usize,
+            _ => lump as usize
+        }
+    }
+    /// Entity text always opens with `{`, so a first lump starting with anything else holds the planes.
+    fn detect_swap(&mut self, first_byte: Option<u8>)
+    {
+        self.swapped = first_byte != Some( b'{' ) && self.lumps[Lump::Planes as usize].length > 0;
+    }
+}
+/// An entity from the entity lump. Keyvalues are kept in the order they appear in the map.
+#[derive( Clone, Debug, Default, PartialEq )]
+pub struct Entity
+{
+    pub keyvalues: Vec<(String, String)>
+}
+
+impl Entity
+{   /// Gets a value by key. If the key appears more than once the last one wins, same as in game.
+    pub fn get(&self, key: &str) -> Option<&str>
+    {
+        self.keyvalues
+            .iter()
+            .rev()
+            .find( |( k, _ )| k.eq_ignore_ascii_case( key ) )
+        .map( |( _, v )| v.as_str() )
+    }
+
+    pub fn classname(&self) -> &str
+    {
+        self.get( "classname" ).unwrap_or_default()
+    }
+}
+/// Parses the text of an entity lump: `{ "key" "value" ... }` blocks.
+pub fn parse_entities(text: &str) -> io::Result<Vec<Entity>>
+{
+    let mut entities = vec![];
+    let mut current: Option<Entity> = None;
+    let mut chars = text.char_indices();
+    let mut pending_key: Option<String> = None;
+
+    while let Some( ( i, c ) ) = chars.next()
+    {
+        match c
+        {
+            '{' if current.is_none() => current = Some( Entity::default() ),
+            '}' if pending_key.is_none() =>
+            {
+                let entity = current.take().ok_or_else( || invalid( &format!( "Unexpected '}}' at byte {i} of the entity lump." ) ) )?;
+                entities.push( entity );
+            }
+
+            '"' =>
+            {
+                let Some( entity ) = current.as_mut()
+                else
+                {
+                    return Err( invalid( &format!( "Keyvalue outside of an entity at byte {i} of the entity lump." ) ) );
+                };
+
+                let mut token = String::new();
+
+                loop
+                {
+                    match chars.next()
+                    {
+                        Some( ( _, '"' ) ) => break,
+                        Some( ( _, c ) ) => token.push( c ),
+                        None => return Err( invalid( &format!( "Unterminated string at byte {i} of the entity lump." ) ) )
+                    }
+                }
+
+                match pending_key.take()
+                {
+                    Some( key ) => entity.keyvalues.push( ( key, token ) ),
+                    None => pending_key = Some( token )
+                }
+            }
+
+            '\0' => break,
+            c if c.is_whitespace() => { }
+            c => return Err( invalid( &format!( "Unexpected '{c}' at byte {i} of the entity lump." ) ) )
+        }
+    }
+
+    if current.is_some() || pending_key.is_some()
+    {
+        return Err( invalid( "Entity lump ends in the middle of an entity." ) );
+    }
+
+    Ok( entities )
+}
+/// A texture from the texture lump of a map.
+#[derive( Clone, Debug )]
+pub struct BspTexture
+{
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Whether the image is compiled into the map. Otherwise the game loads it from one of the map's WADs.
+    pub embedded: bool,
+    /// Whether any face of the map is drawn with it.
+    pub used: bool,
+    /// Where the texture starts in the texture lump.
+    offset: usize
+}
+/// A loaded GoldSrc BSP v30 map.
+#[derive( Clone, Debug )]
+pub struct Bsp
+{
+    pub header: BspHeader,
+    pub entities: Vec<Entity>,
+    data: Vec<u8>
+}
+
+impl Bsp
+{
+    pub fn open(path: &Path) -> io::Result<Self>
+    {
+        Self::from_bytes( fs::read( path )? )
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self>
+    {
+        let header = BspHeader::from_bytes( &data )?;
+
+        let entities = parse_entities( &String::from_utf8_lossy( &data[header.lump( Lump::Entities ).range()] ) )?;
+
+        Ok( Self { header, entities, data } )
+    }
+    /// Raw bytes of a lump.
+    pub fn lump(&self, lump: Lump) -> &[u8]
+    {
+        &self.data[self.header.lump( lump ).range()]
+    }
+
+    pub fn worldspawn(&self) -> Option<&Entity>
+    {
+        self.entities.iter().find( |e| e.classname().eq_ignore_ascii_case( "worldspawn" ) )
+    }
+    /// File names of the WADs the map uses, without the folders the compiler stored with them.
+    pub fn wads(&self) -> Vec<String>
+    {
+        self.worldspawn()
+            .and_then( |w| w.get( "wad" ) )
+            .unwrap_or_default()
+            .split( ';' )
+            .map( |w| w.rsplit( ['/', '\\'] ).next().unwrap_or( w ).trim() )
+            .filter( |w| !w.is_empty() )
+            .map( str::to_owned )
+        .collect()
+    }
+
+    pub fn skyname(&self) -> Option<&str>
+    {
+        self.worldspawn()?.get( "skyname" ).filter( |s| !s.is_empty() )
+    }
+
+    pub fn message(&self) -> Option<&str>
+    {
+        self.worldspawn()?.get( "message" ).filter( |s| !s.is_empty() )
+    }
+    /// The textures in the texture lump, in the order the map refers to them.
+    pub fn textures(&self) -> io::Result<Vec<BspTexture>>
+    {
+        let lump = self.lump( Lump::Textures );
+        let read_i32 = |at: usize| lump.get( at..at + 4 ).map( |b| i32::from_le_bytes( [b[0], b[1], b[2], b[3]] ) );
+
+        let count = read_i32( 0 ).unwrap_or_default();
+
+        if count < 0 || 4 + count as usize * 4 > lump.len()
+        {
+            return Err( invalid( "The texture lump has more textures than fit in it." ) );
+        }
+
+        let used = self.used_textures( count as usize );
+        let mut textures = vec![];
+
+        for ( i, used ) in used.into_iter().enumerate()
+        {
+            let offset = read_i32( 4 + i * 4 ).unwrap_or( -1 );
+            // Compilers leave -1 for textures they could not find
+            if offset < 0
+            {
+                continue;
+            }
+
+            let offset = offset as usize;
+
+            let ( Some( name ), Some( width ), Some( height ), Some( pixels ) ) =
+                ( lump.get( offset..offset + 16 ), read_i32( offset + 16 ), read_i32( offset + 20 ), read_i32( offset + 24 ) )
+            else
+            {
+                return Err( invalid( &format!( "Texture {i} is outside the texture lump." ) ) );
+            };
+
+            let end = name.iter().position( |&b| b == 0 ).unwrap_or( name.len() );
+
+            textures.push( BspTexture
+            {
+                name: String::from_utf8_lossy( &name[..end] ).trim().to_owned(),
+                width: width.max( 0 ) as u32,
+                height: height.max( 0 ) as u32,
+                embedded: pixels != 0,
+                used,
+                offset
+            });
+        }
+
+        Ok( textures )
+    }
+    /// Decodes an embedded texture to RGBA at the given mip level.
+    pub fn decode_texture(&self, texture: &BspTexture, mip: usize) -> io::Result<Image>
+    {
+        decode_miptex( self.lump( Lump::Textures ).get( texture.offset.. ).unwrap_or_default(), mip )
+    }
+    /// Which textures the faces of the map are drawn with, by their index in the texture lump.
+    fn used_textures(&self, count: usize) -> Vec<bool>
+    {
+        let texinfo = self.lump( Lump::TexInfo );
+        let mut used = vec![false; count];
+
+        for face in self.lump( Lump::Faces ).chunks_exact( FACE_SIZE )
+        {
+            let info = u16::from_le_bytes( [face[10], face[11]] ) as usize;
+
+            if let Some( entry ) = texinfo.get( info * TEXINFO_SIZE..( info + 1 ) * TEXINFO_SIZE )
+            && let Ok( miptex ) = usize::try_from( i32::from_le_bytes( [entry[32], entry[33], entry[34], entry[35]] ) )
+            && let Some( u ) = used.get_mut( miptex )
+            {
+                *u = true;
+            }
+        }
+
+        used
+    }
+}
+/// Reads only the entities of a BSP, without loading the rest of the file.
+pub fn read_entities(path: &Path) -> io::Result<Vec<Entity>>
+{
+    let header = BspHeader::read( path )?;
+    let lump = header.lump( Lump::Entities );
+
+    let mut file = File::open( path )?;
+    file.seek( SeekFrom::Start( lump.offset as u64 ) )?;
+
+    let mut bytes = vec![0u8; lump.length as usize];
+    file.read_exact( &mut bytes )?;
+
+    parse_entities( &String::from_utf8_lossy( &bytes ) )
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new( io::ErrorKind::InvalidData, message.to_owned() )
+}
@@ -0,0 +1,482 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    collections::
+    {
+        BTreeMap,
+        HashMap
+    },
+    fs,
+    io,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+use serde::Serialize;
+
+use crate::
+{
+    backup::BackupStore,
+    bsp::
+    {
+        self,
+        BspHeader,
+        Lump
+    },
+    cfgdoc::
+    {
+        CfgDocument,
+        read_text
+    },
+    config::Config,
+    cvar::
+    {
+        EXT_BSP,
+        filter_bsps,
+        load_bsps
+    },
+    replacements::
+    {
+        GameRoots,
+        Replacement,
+        tokenize
+    },
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
+    resources::
+    {
+        CFG_REFERENCES,
+        Resource,
+        cfg_candidates,
+        content_dirs,
+        normalize
+    },
+    utils::
+    {
+        HasExtension,
+        write_atomic
+    }
+};
+/// A path written in a map's files, with the game paths it could stand for.
+struct Reference
+{
+    file: PathBuf,
+    raw: String,
+    paths: Vec<String>
+}
+/// A reference that only finds its file when case is ignored, so it breaks on Linux servers.
+#[derive( Clone, Debug, Serialize )]
+pub struct CaseIssue
+{
+    /// File the reference is written in.
+    pub file: PathBuf,
+    /// The reference as written.
+    pub reference: String,
+    /// Game path of the file, spelled as on disk.
+    pub actual: String,
+    /// Content folder the file is in.
+    pub dir: String
+}
+
+impl CaseIssue
+{   /// The reference respelled to match the file on disk.
+    pub fn fixed(&self) -> String
+    {
+        respell( &self.reference, &self.actual )
+    }
+}
+/// Checks that the paths a map's files refer to match the case of the files on disk.
+#[derive( Clone, Debug, Default, Serialize )]
+pub struct CaseCheck
+{
+    /// Number of references checked.
+    pub checked: usize,
+    pub issues: Vec<CaseIssue>
+}
+
+impl CaseCheck
+{   /// Checks the maps against their own game folder and the content folders of the install from the config.
+    pub fn run(maps_dir: &Path, whitelist: &[String]) -> io::Result<Self>
+    {
+        let install_dir = Config::get().svencoopdir.as_deref().and_then( Path::parent ).map( Path::to_path_buf );
+        Self::run_in( install_dir.as_deref(), maps_dir, whitelist )
+    }
+    /// Checks the entity keyvalues of each BSP, the path CVars in its cfg, and both sides of every entry in the
+    /// replacement files the cfg names. Only references that differ from a file on disk by case are reported,
+    /// files that can't be found at all are left to `ResourceCheck`.
+    pub fn run_in(install_dir: Option<&Path>, maps_dir: &Path, whitelist: &[String]) -> io::Result<Self>
+    {
+        let bsps = filter_bsps( load_bsps( maps_dir ), whitelist );
+
+        if bsps.is_empty()
+        {
+            return Err( io::Error::new( io::ErrorKind::NotFound, "No matching BSP files found." ) );
+        }
+
+        let game_dir = maps_dir.parent().unwrap_or( maps_dir ).to_path_buf();
+        let mut dirs = install_dir.map( content_dirs ).unwrap_or_default();
+        dirs.retain( |d| *d != game_dir );
+        dirs.insert( 0, game_dir );
+
+        let roots = GameRoots { roots: dirs.clone() };
+        let mut disk = DiskNames::default();
+        let mut check = Self::default();
+
+        for bsp_path in &bsps
+        {
+            let mut references = entity_references( bsp_path );
+
+            let cfg_path = bsp_path.with_extension( "cfg" );
+            let map = bsp_path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+
+            if let Ok( cfg ) = CfgDocument::from_file( &cfg_path )
+            {
+                for ( name, _ ) in CFG_REFERENCES
+                {
+                    let Some( cvar ) = cfg.get( name ).filter( |c| !c.value.is_empty() )
+                    else
+                    {
+                        continue;
+                    };
+
+                    let paths = cfg_candidates( name, &map, &normalize( &cvar.value ) );
+
+                    if name.starts_with( "global" )
+                    && let Some( ( dir, actual ) ) = paths.iter().find_map( |p| disk.find_any( &dirs, p ) )
+                    {
+                        references.extend( replacement_references( &dir.join( actual ), &roots ) );
+                    }
+
+                    references.push( Reference { file: cfg_path.clone(), raw: cvar.value.clone(), paths } );
+                }
+            }
+
+            for reference in references
+            {
+                check.checked += 1;
+
+                if let Some( issue ) = disk.check( &dirs, reference )
+                && !check.issues.iter().any( |i| i.file == issue.file && i.reference == issue.reference )
+                {
+                    check.issues.push( issue );
+                }
+            }
+        }
+
+        Ok( check )
+    }
+    /// Rewrites every reported reference to the spelling on disk, backing up the files first.
+    pub fn fix(&self) -> io::Result<Report>
+    {
+        self.fix_with_backup( &BackupStore::open() )
+    }
+    /// Same as `fix`, backing the files up into the given store.
+    pub fn fix_with_backup(&self, store: &BackupStore) -> io::Result<Report>
+    {
+        let mut by_file: BTreeMap<&Path, Vec<&CaseIssue>> = BTreeMap::new();
+
+        for issue in &self.issues
+        {
+            by_file.entry( &issue.file ).or_default().push( issue );
+        }
+
+        let files: Vec<_> = by_file.keys().map( |f| f.to_path_buf() ).collect();
+        let set = store.snapshot( "fix case", &files )?;
+
+        let report = by_file
+            .into_iter()
+            .map( |( file, issues )| FileReport::from_result( file, fix_file( file, &issues ) ) )
+        .collect();
+
+        store.keep( set, &report )?;
+        Ok( report )
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String>
+    {
+        serde_json::to_string_pretty( self )
+    }
+
+    pub fn to_markdown(&self) -> String
+    {
+        let mut out = format!( "# Case check\n\n{} reference(s) checked, {} only match when case is ignored\n",
+            self.checked, self.issues.len() );
+
+        if !self.issues.is_empty()
+        {
+            out.push_str( "\n| File | Reference | On disk | Folder |\n" );
+            out.push_str( "|------|-----------|---------|--------|\n" );
+
+            for issue in &self.issues
+            {
+                out.push_str( &format!( "| {} | {} | {} | {} |\n",
+                    issue.file.display(), issue.reference, issue.actual, issue.dir ) );
+            }
+        }
+
+        out
+    }
+}
+/// Lists the file references in a BSP's entities. Unreadable BSPs have none, `ResourceCheck` reports those.
+fn entity_references(bsp_path: &Path) -> Vec<Reference>
+{
+    bsp::read_entities( bsp_path )
+        .unwrap_or_default()
+        .iter()
+        .flat_map( |e| e.keyvalues.iter() )
+        .filter_map( |( _, value )| Some( Reference
+        {
+            file: bsp_path.to_path_buf(),
+            raw: value.clone(),
+            paths: vec![Resource::from_value( value, "" )?.path]
+        }))
+    .collect()
+}
+/// Lists both sides of each entry in a gmr/gsr file, read the same way the file is parsed for use.
+fn replacement_references(path: &Path, roots: &GameRoots) -> Vec<Reference>
+{
+    let Ok( ( text, _ ) ) = read_text( path )
+    else
+    {
+        return vec![];
+    };
+
+    let parsed = Replacement::parse( &text, roots );
+    let lines: Vec<_> = text.lines().collect();
+    let mut references = vec![];
+
+    for ( replacement, line ) in parsed.replacements.iter().zip( parsed.lines )
+    {
+        let Ok( tokens ) = tokenize( lines[line - 1] )
+        else
+        {
+            continue;
+        };
+
+        for ( ( _, raw ), value ) in tokens.iter().zip( [replacement.get_original(), replacement.get_new()] )
+        {
+            if let Some( resource ) = Resource::from_value( value, "" )
+            {
+                references.push( Reference { file: path.to_path_buf(), raw: raw.clone(), paths: vec![resource.path] } );
+            }
+        }
+    }
+
+    references
+}
+/// Respells the paths in a gmr/gsr file that match a reference, whether quoted or not. Comments are left alone.
+fn fix_replacements(text: &str, issues: &[&CaseIssue]) -> String
+{
+    text
+        .split_inclusive( '\n' )
+        .map( |line|
+        {
+            let Ok( tokens ) = tokenize( line )
+            else
+            {
+                return line.to_owned();
+            };
+
+            let mut chars: Vec<_> = line.chars().collect();
+            // From the end, so respelling a path never moves the ones before it
+            for ( column, token ) in tokens.iter().rev()
+            {
+                if let Some( issue ) = issues.iter().find( |i| i.reference == *token )
+                {   // Quoted paths start at their quote and are trimmed, so skip those
+                    let start = column - 1 + chars[column - 1..].iter().take_while( |&&c| c == '"' || c.is_whitespace() ).count();
+                    chars.splice( start..start + token.chars().count(), issue.fixed().chars() );
+                }
+            }
+
+            chars.into_iter().collect::<String>()
+        })
+    .collect()
+}
+/// Rewrites the references in one file.
+fn fix_file(file: &Path, issues: &[&CaseIssue]) -> io::Result<Outcome>
+{
+    if file.has_extension( &[EXT_BSP] )
+    {
+        let mut data = fs::read( file )?;
+        let lump = BspHeader::from_bytes( &data )?.lump( Lump::Entities );
+        let entities = &mut data[lump.offset as usize..( lump.offset + lump.length ) as usize];
+
+        for issue in issues
+        {   // Only the case changes, so the lump keeps its size and nothing else in the file moves
+            let ( from, to ) = ( format!( "\"{}\"", issue.reference ), format!( "\"{}\"", issue.fixed() ) );
+            replace_bytes( entities, from.as_bytes(), to.as_bytes() );
+        }
+
+        write_atomic( file, data )?;
+    }
+    else if file.has_extension( &["cfg"] )
+    {
+        let mut doc = CfgDocument::from_file( file )?;
+
+        for issue in issues
+        {
+            doc.replace_value( &issue.reference, &issue.fixed() );
+        }
+
+        write_atomic( file, doc.to_bytes()? )?;
+    }
+    else
+    {
+        let ( text, encoding ) = read_text( file )?;
+        write_atomic( file, encoding.encode( &fix_replacements( &text, issues ) )? )?;
+    }
+
+    Ok( Outcome::Written )
+}
+/// Replaces every occurrence of `from` with `to` in place. Both must be the same length.
+fn replace_bytes(data: &mut [u8], from: &[u8], to: &[u8])
+{
+    let mut i = 0;
+
+    while i + from.len() <= data.len()
+    {
+        if data[i..].starts_with( from )
+        {
+            data[i..i + to.len()].copy_from_slice( to );
+            i += from.len();
+        }
+        else
+        {
+            i += 1;
+        }
+    }
+}
+/// Copies the case of `actual` onto `reference`, matching from the end as the reference may leave out
+/// a leading folder like `sound/`. Stops at the first character that differs by more than case.
+fn respell(reference: &str, actual: &str) -> String
+{
+    let is_separator = |c: &char| *c == '/' || *c == '\\';
+    let mut actual = actual.chars().rev().filter( |c| !is_separator( c ) ).peekable();
+
+    let mut respelled: Vec<char> = reference
+        .chars()
+        .rev()
+        .map( |c|
+        {
+            if is_separator( &c )
+            {
+                return c;
+            }
+
+            match actual.peek()
+            {
+                Some( &a ) if a.eq_ignore_ascii_case( &c ) =>
+                {
+                    actual.next();
+                    a
+                }
+
+                _ =>
+                {   // Drain so nothing further is changed
+                    actual.by_ref().for_each( drop );
+                    c
+                }
+            }
+        })
+    .collect();
+
+    respelled.reverse();
+    respelled.into_iter().collect()
+}
+/// Caches the names in each folder, so references can be matched case-sensitively on any OS.
+#[derive( Default )]
+pub(crate) struct DiskNames
+{
+    dirs: HashMap<PathBuf, Vec<String>>
+}
+
+impl DiskNames
+{
+    fn names(&mut self, dir: &Path) -> &[String]
+    {
+        self.dirs.entry( dir.to_path_buf() ).or_insert_with( ||
+        {
+            fs::read_dir( dir )
+                .map( |rd| rd.filter_map( Result::ok ).map( |e| e.file_name().to_string_lossy().into_owned() ).collect() )
+            .unwrap_or_default()
+        })
+    }
+    /// Spells a game path the way it is on disk in a folder, an exact match is preferred over one that ignores case.
+    pub(crate) fn spell(&mut self, dir: &Path, path: &str) -> Option<String>
+    {
+        let mut current = dir.to_path_buf();
+        let mut parts = vec![];
+
+        for part in path.split( '/' )
+        {
+            let names = self.names( &current );
+
+            let name = names
+                .iter()
+                .find( |n| *n == part )
+                .or_else( || names.iter().find( |n| n.eq_ignore_ascii_case( part ) ) )?
+            .clone();
+
+            current.push( &name );
+            parts.push( name );
+        }
+
+        current.is_file().then( || parts.join( "/" ) )
+    }
+    /// Finds a game path in the first folder that has it, ignoring case.
+    fn find_any<'a>(&mut self, dirs: &'a [PathBuf], path: &str) -> Option<(&'a Path, String)>
+    {
+        dirs.iter().find_map( |d| Some( ( d.as_path(), self.spell( d, path )? ) ) )
+    }
+    /// Reports a reference that has no exact match in any folder but does when case is ignored.
+    fn check(&mut self, dirs: &[PathBuf], reference: Reference) -> Option<CaseIssue>
+    {
+        let mut near_miss = None;
+
+        for path in &reference.paths
+        {
+            for dir in dirs
+            {
+                match self.spell( dir, path )
+                {
+                    Some( actual ) if actual == *path => return None,
+                    Some( actual ) if near_miss.is_none() => near_miss = Some( ( dir, actual ) ),
+                    _ => { }
+                }
+            }
+        }
+
+        let ( dir, actual ) = near_miss?;
+
+        Some( CaseIssue
+        {
+            file: reference.file,
+            reference: reference.raw,
+            actual,
+            dir: dir.file_name().unwrap_or_default().to_string_lossy().into_owned()
+        })
+    }
+}
@@ -0,0 +1,505 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs,
+    io,
+    path::Path
+};
+
+const BOM: char = '\u{FEFF}';
+/// How the text of a CFG file is stored on disk.
+#[derive( Clone, Copy, Debug, Default, PartialEq )]
+pub enum Encoding
+{
+    #[default]
+    Utf8,
+    /// Anything that isn't valid UTF-8, like files saved with a Windows code page.
+    /// Each byte is read as the character with the same number, so every byte is written back as it was.
+    Latin1
+}
+
+impl Encoding
+{   /// Decodes a file's bytes, picking the encoding that reads them without losing any.
+    pub fn decode(bytes: Vec<u8>) -> (String, Self)
+    {
+        match String::from_utf8( bytes )
+        {
+            Ok( text ) => ( text, Self::Utf8 ),
+            Err( e ) => ( e.into_bytes().into_iter().map( char::from ).collect(), Self::Latin1 )
+        }
+    }
+    /// Encodes text to write back, fails if it has characters the encoding can't store.
+    pub fn encode(self, text: &str) -> io::Result<Vec<u8>>
+    {
+        match self
+        {
+            Self::Utf8 => Ok( text.as_bytes().to_vec() ),
+            Self::Latin1 => text
+                .chars()
+                .map( |c| u8::try_from( c ).map_err( |_| io::Error::new( io::ErrorKind::InvalidData,
+                    format!( "'{c}' can't be saved in a file that isn't UTF-8." ) ) ) )
+            .collect()
+        }
+    }
+}
+/// Reads a CFG file's text and the encoding to write it back with.
+pub fn read_text(path: &Path) -> io::Result<(String, Encoding)>
+{
+    Ok( Encoding::decode( fs::read( path )? ) )
+}
+/// A single `name value` line in a CFG file.
+#[derive( Clone, Debug, PartialEq )]
+pub struct Cvar
+{
+    pub name: String,
+    pub value: String,
+    pub quoted: bool,
+    pub comment: Option<String>
+}
+
+impl Cvar
+{
+    pub fn new(name: &str, value: &str) -> Self
+    {
+        Self
+        {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            quoted: value.contains( char::is_whitespace ),
+            comment: None
+        }
+    }
+    /// CVar names are case-insensitive in game.
+    pub fn is_named(&self, name: &str) -> bool
+    {
+        self.name.eq_ignore_ascii_case( name )
+    }
+    /// Checks if both set the same CVar to the same value, ignoring quotes and comments.
+    pub fn same_setting(&self, other: &Cvar) -> bool
+    {
+        self.is_named( &other.name ) && self.value == other.value
+    }
+    /// Checks if both hold the same value, so `1` and `1.0` count as the same number.
+    pub fn same_value(&self, other: &Cvar) -> bool
+    {
+        if self.value == other.value
+        {
+            return true;
+        }
+
+        match ( self.value.parse::<f64>(), other.value.parse::<f64>() )
+        {
+            ( Ok( a ), Ok( b ) ) => a == b,
+            _ => false
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self>
+    {
+        let line = line.trim();
+
+        if line.starts_with( '"' )
+        {
+            return None;
+        }
+
+        let ( name, rest ) = line.split_once( char::is_whitespace ).unwrap_or( ( line, "" ) );
+        let rest = rest.trim_start();
+
+        let ( value, quoted, rest ) =
+        if let Some( quoted ) = rest.strip_prefix( '"' )
+        {   // Unterminated quotes and junk after the closing quote are malformed
+            let ( value, rest ) = quoted.split_once( '"' )?;
+
+            if !rest.trim().is_empty() && !rest.trim().starts_with( "//" )
+            {
+                return None;
+            }
+
+            ( value, true, rest )
+        }
+        else
+        {
+            match rest.find( "//" )
+            {
+                Some( i ) => ( rest[..i].trim_end(), false, &rest[i..] ),
+                None => ( rest.trim_end(), false, "" )
+            }
+        };
+
+        let comment = rest
+            .trim()
+            .strip_prefix( "//" )
+        .map( |c| c.trim().to_owned() );
+
+        Some( Self { name: name.to_owned(), value: value.to_owned(), quoted, comment } )
+    }
+}
+
+impl Display for Cvar
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!( f, "{}", self.name )?;
+
+        if self.quoted
+        {
+            write!( f, " \"{}\"", self.value )?;
+        }
+        else if !self.value.is_empty()
+        {
+            write!( f, " {}", self.value )?;
+        }
+
+        match &self.comment
+        {
+            Some( comment ) => write!( f, " // {comment}" ),
+            None => Ok( () )
+        }
+    }
+}
+/// What a line in a CFG file holds.
+#[derive( Clone, Debug, PartialEq )]
+pub enum Entry
+{
+    Cvar( Cvar ),
+    /// Whole line comment, including its `//` or `#` marker.
+    Comment( String ),
+    /// Line that could not be read as a CVar, kept as is.
+    Malformed( String ),
+    Blank
+}
+
+impl Entry
+{
+    fn parse(line: &str) -> Self
+    {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty()
+        {
+            Self::Blank
+        }
+        else if trimmed.starts_with( "//" ) || trimmed.starts_with( '#' )
+        {
+            Self::Comment( trimmed.to_owned() )
+        }
+        else
+        {
+            Cvar::parse( trimmed ).map_or_else( || Self::Malformed( trimmed.to_owned() ), Self::Cvar )
+        }
+    }
+}
+
+impl Display for Entry
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::Cvar( cvar ) => write!( f, "{cvar}" ),
+            Self::Comment( text ) | Self::Malformed( text ) => write!( f, "{text}" ),
+            Self::Blank => Ok( () )
+        }
+    }
+}
+
+#[derive( Clone, Debug )]
+struct Line
+{
+    entry: Entry,
+    /// Original text of the line, dropped once the entry is edited.
+    raw: Option<String>,
+    ending: &'static str,
+    /// Line and column, from 1, the entry starts at in the text it was read from. Lines added later have none.
+    position: Option<(usize, usize)>
+}
+
+impl Line
+{
+    fn new(entry: Entry) -> Self
+    {
+        Self { entry, raw: None, ending: "\n", position: None }
+    }
+}
+/// Ordered, comment-preserving model of a CFG file.
+/// Lines that are not edited are written back exactly as they were read.
+#[derive( Clone, Debug, Default )]
+pub struct CfgDocument
+{
+    lines: Vec<Line>,
+    /// Whether the file starts with a UTF-8 byte order mark, written back ahead of the first line.
+    bom: bool,
+    /// Encoding the file was read with, see `to_bytes`.
+    encoding: Encoding
+}
+
+impl CfgDocument
+{
+    pub fn parse(text: &str) -> Self
+    {
+        let ( text, bom ) =
+        match text.strip_prefix( BOM )
+        {
+            Some( text ) => ( text, true ),
+            None => ( text, false )
+        };
+
+        let lines = text
+            .split_inclusive( '\n' )
+            .enumerate()
+            .map( |( idx, line )|
+            {
+                let ( raw, ending ) =
+                if let Some( raw ) = line.strip_suffix( "\r\n" )
+                {
+                    ( raw, "\r\n" )
+                }
+                else if let Some( raw ) = line.strip_suffix( '\n' )
+                {
+                    ( raw, "\n" )
+                }
+                else
+                {
+                    ( line, "" )
+                };
+
+                let column = raw.chars().take_while( |c| c.is_whitespace() ).count() + 1;
+
+                Line { entry: Entry::parse( raw ), raw: Some( raw.to_owned() ), ending, position: Some( ( idx + 1, column ) ) }
+            })
+        .collect();
+
+        Self { lines, bom, encoding: Encoding::Utf8 }
+    }
+    /// Reads a CFG file. Files that aren't valid UTF-8 are read byte for byte, see `Encoding`.
+    pub fn from_file(path: &Path) -> io::Result<Self>
+    {
+        let ( text, encoding ) = read_text( path )?;
+        Ok( Self { encoding, ..Self::parse( &text ) } )
+    }
+    /// The document as it is written to disk, in the encoding it was read with.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>>
+    {
+        self.encoding.encode( &self.to_string() )
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry>
+    {
+        self.lines.iter().map( |l| &l.entry )
+    }
+
+    /// The entries read from the text, with the line and column (in characters, from 1) each starts at.
+    pub fn located_entries(&self) -> impl Iterator<Item = (usize, usize, &Entry)>
+    {
+        self.lines.iter().filter_map( |l| l.position.map( |( line, column )| ( line, column, &l.entry ) ) )
+    }
+
+    pub fn cvars(&self) -> impl Iterator<Item = &Cvar>
+    {
+        self.entries().filter_map( |e| match e
+        {
+            Entry::Cvar( cvar ) => Some( cvar ),
+            _ => None
+        })
+    }
+    /// Gets the CVar by name. If it is set more than once the last one wins, same as in game.
+    pub fn get(&self, name: &str) -> Option<&Cvar>
+    {
+        self.cvars().filter( |c| c.is_named( name ) ).last()
+    }
+    /// Adds an entry on a new line at the end of the document.
+    pub fn push(&mut self, entry: Entry)
+    {
+        if let Some( last ) = self.lines.last_mut()
+        && last.ending.is_empty()
+        {
+            last.ending = "\n";
+        }
+
+        self.lines.push( Line::new( entry ) );
+    }
+    /// Adds the lines of another document to the end of this one, keeping their text as is.
+    pub fn append(&mut self, other: CfgDocument)
+    {
+        if let Some( last ) = self.lines.last_mut()
+        && last.ending.is_empty()
+        && !other.lines.is_empty()
+        {
+            last.ending = "\n";
+        }
+
+        self.lines.extend( other.lines );
+    }
+    /// Sets a CVar's value in place, dropping any other lines that set it again.
+    /// If the CVar is not in the document it is added to the end.
+    pub fn set(&mut self, cvar: Cvar)
+    {
+        let mut found = false;
+
+        self.lines.retain_mut( |l|
+        {
+            let Entry::Cvar( existing ) = &mut l.entry
+            else
+            {
+                return true;
+            };
+
+            if !existing.is_named( &cvar.name )
+            {
+                return true;
+            }
+
+            if found
+            {
+                return false;
+            }
+
+            found = true;
+
+            if existing.value != cvar.value || existing.quoted != cvar.quoted
+            {
+                existing.value = cvar.value.clone();
+                existing.quoted = cvar.quoted;
+                existing.comment = cvar.comment.clone().or( existing.comment.take() );
+                l.raw = None;
+            }
+
+            true
+        });
+
+        if !found
+        {
+            self.push( Entry::Cvar( cvar ) );
+        }
+    }
+    /// Changes the value of every CVar set to `from`, editing the value in the line's own text so its spacing,
+    /// quotes and comment stay as they were. Duplicate lines are kept. Returns how many lines were changed.
+    pub fn replace_value(&mut self, from: &str, to: &str) -> usize
+    {
+        let mut changed = 0;
+
+        for line in &mut self.lines
+        {
+            let Entry::Cvar( cvar ) = &mut line.entry
+            else
+            {
+                continue;
+            };
+
+            if cvar.value != from
+            {
+                continue;
+            }
+
+            if let Some( raw ) = &mut line.raw
+            {   // The value comes after the name, which is the first thing on the line
+                let start = raw.find( cvar.name.as_str() ).unwrap_or( 0 ) + cvar.name.len();
+
+                if let Some( i ) = raw[start..].find( from )
+                {
+                    raw.replace_range( start + i..start + i + from.len(), to );
+                }
+            }
+
+            cvar.value = to.to_owned();
+            changed += 1;
+        }
+
+        changed
+    }
+    /// Removes every line that sets the named CVar, returns true if any were removed.
+    pub fn remove(&mut self, name: &str) -> bool
+    {
+        let len = self.lines.len();
+        self.retain_cvars( |c| !c.is_named( name ) );
+
+        self.lines.len() != len
+    }
+    /// Lists the CVars that end up with a different value than in `defaults`, in the order they are first set.
+    /// CVars set more than once are listed once, with the value that wins.
+    pub fn overrides(&self, defaults: &CfgDocument) -> Vec<&Cvar>
+    {
+        let mut names: Vec<&str> = vec![];
+
+        for cvar in self.cvars()
+        {
+            if !names.iter().any( |n| cvar.is_named( n ) )
+            {
+                names.push( &cvar.name );
+            }
+        }
+
+        names
+            .into_iter()
+            .filter_map( |name| self.get( name ) )
+            .filter( |cvar| !defaults.get( &cvar.name ).is_some_and( |d| d.same_value( cvar ) ) )
+        .collect()
+    }
+    /// Removes the CVars that end up with the same value as in `defaults`, returns how many lines were removed.
+    pub fn minimize(&mut self, defaults: &CfgDocument) -> usize
+    {
+        let redundant: Vec<String> = self
+            .cvars()
+            .filter( |cvar| self.get( &cvar.name ).is_some_and( |last| defaults.get( &last.name ).is_some_and( |d| d.same_value( last ) ) ) )
+            .map( |cvar| cvar.name.clone() )
+        .collect();
+
+        let len = self.lines.len();
+        self.retain_cvars( |c| !redundant.iter().any( |name| c.is_named( name ) ) );
+
+        len - self.lines.len()
+    }
+    /// Keeps only the CVar lines for which `keep` returns true, other lines are left alone.
+    pub fn retain_cvars(&mut self, mut keep: impl FnMut(&Cvar) -> bool)
+    {
+        self.lines.retain( |l| match &l.entry
+        {
+            Entry::Cvar( cvar ) => keep( cvar ),
+            _ => true
+        });
+    }
+}
+
+impl Display for CfgDocument
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        if self.bom
+        {
+            write!( f, "{BOM}" )?;
+        }
+
+        for line in &self.lines
+        {
+            match &line.raw
+            {
+                Some( raw ) => write!( f, "{raw}{}", line.ending )?,
+                None => write!( f, "{}{}", line.entry, line.ending )?
+            }
+        }
+
+        Ok( () )
+    }
+}
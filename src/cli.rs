@@ -0,0 +1,641 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fs,
+    io::
+    {
+        self,
+        Read
+    },
+    path::
+    {
+        Path,
+        PathBuf
+    },
+    process::ExitCode
+};
+
+use crate::
+{
+    APPNAME,
+    audit::Audit,
+    backup::BackupStore,
+    bsp::Bsp,
+    casecheck::CaseCheck,
+    current_dir_path,
+    lint,
+    package::package_maps,
+    prelude::*,
+    rescheck::ResourceCheck,
+    resources::
+    {
+        build_res,
+        create_res
+    },
+    schema::schema,
+    wav
+};
+
+pub const COMMANDS: [&str; 15] = ["cfg", "minimize", "restore", "motd", "res", "package", "replace", "materials", "wad", "lint", "audit", "check", "case", "bsp", "help"];
+
+fn usage() -> String
+{
+    format!( "\
+Usage: {APPNAME} <command> [options] [files...]
+
+Commands:
+  cfg [file]          Writes the CVars in 'file' (or stdin when '-') to the cfg of every BSP
+  minimize            Removes CVars from every BSP's cfg that are the same as the server defaults
+  restore             Undoes the last cfg, minimize, res or case fix run, putting back the files as they were
+  motd <file>         Copies 'file' to a '<map>_motd.txt' for every BSP
+  res                 Writes a '<map>.res' listing the custom content every BSP needs clients to download
+  package             Zips every BSP with its companion files, custom content and a '<map>_manifest.json'
+  replace <files...>  Normalises .gmr/.gsr files and writes them back out, merging, inverting or collapsing them if asked
+  materials <file>    Normalises a materials .txt file and writes it back out
+  wad <files...>      Lists the texture names in .wad files
+  lint <files...>     Checks cfg files for unknown CVars, bad values, duplicates and malformed lines
+  audit               Reports the companion files each BSP has, missing referenced files and orphaned files
+  check               Finds every file each BSP needs in the game's content folders, reporting missing and shadowed files
+  case                Finds file references in each BSP, its cfg and replacement files that only match the file on disk when case is ignored
+  bsp <files...>      Checks .bsp files and shows their entity count and worldspawn wad, skyname and message
+  help                Shows this message
+
+Options:
+  --maps-dir <dir>    Folder containing the BSPs (default: the executable's folder)
+  --write-type <type> overwrite, append, remove, delete, set, unset, merge or minimize (default: overwrite)
+  --skill             Writes '<map>_skl.cfg' files instead of '<map>.cfg'
+  --bsp <names>       Only process these BSPs, comma separated or repeated
+  --dry-run           Prints a diff of what 'cfg' or 'minimize' would change, or the files 'res' would list, without writing anything
+  --all-or-nothing    If any cfg fails to be written, puts every other cfg back as it was (cfg/minimize)
+  --fix               Rewrites the references 'case' finds to match the files on disk
+  --merge             Combines the 'replace' files into one, named by --output (default: merged), listing conflicting entries
+  --invert            Swaps the original and new file of every 'replace' entry, to undo a replacement
+  --collapse          Points every 'replace' entry at the end of its chain ('a -> b', 'b -> c' becomes 'a -> c'), listing cycles
+  --overrides         Lists the CVars each cfg changes from the server defaults, instead of minimizing
+  --output <name>     Output filename without extension (replace/materials), or output folder (package, default: the maps folder)
+  --format <format>   md or json (audit/check/case, default: md)
+  --reset-config      Clears the stored config before running" )
+}
+/// Parsed command line options shared by every subcommand.
+#[derive( Default )]
+pub struct Args
+{
+    pub files: Vec<String>,
+    pub maps_dir: Option<PathBuf>,
+    pub write_type: Option<WriteType>,
+    pub skill: bool,
+    pub bsps: Vec<String>,
+    pub output: Option<String>,
+    pub dry_run: bool,
+    pub overrides: bool,
+    pub all_or_nothing: bool,
+    pub fix: bool,
+    pub merge: bool,
+    pub invert: bool,
+    pub collapse: bool,
+    pub format: Option<String>
+}
+
+impl Args
+{
+    pub fn parse(args: &[String]) -> io::Result<Self>
+    {
+        let mut parsed = Self::default();
+        let mut iter = args.iter();
+
+        while let Some( arg ) = iter.next()
+        {
+            let mut value = |name: &str| iter
+                .next()
+                .cloned()
+            .ok_or_else( || io::Error::new( io::ErrorKind::InvalidInput, format!( "Missing value for '{name}'." ) ) );
+
+            match arg.as_str()
+            {
+                "--maps-dir" | "-d" => parsed.maps_dir = Some( PathBuf::from( value( arg )? ) ),
+                "--write-type" | "-w" =>
+                {
+                    let wt = value( arg )?;
+                    parsed.write_type = Some( wt.parse::<WriteType>()
+                        .map_err( |_| io::Error::new( io::ErrorKind::InvalidInput, format!( "Unknown write type '{wt}'." ) ) )? );
+                }
+
+                "--skill" | "-s" => parsed.skill = true,
+                "--bsp" | "-b" =>
+                {
+                    parsed.bsps.extend( value( arg )?
+                        .split( ',' )
+                        .map( str::trim )
+                        .filter( |s| !s.is_empty() )
+                    .map( str::to_owned ) );
+                }
+
+                "--output" | "-o" => parsed.output = Some( value( arg )? ),
+                "--dry-run" | "-n" => parsed.dry_run = true,
+                "--overrides" => parsed.overrides = true,
+                "--all-or-nothing" | "-a" => parsed.all_or_nothing = true,
+                "--fix" => parsed.fix = true,
+                "--merge" => parsed.merge = true,
+                "--invert" => parsed.invert = true,
+                "--collapse" => parsed.collapse = true,
+                "--format" | "-f" => parsed.format = Some( value( arg )? ),
+                "--reset-config" | "-reset" | "-r" => { }
+                "-" => parsed.files.push( arg.clone() ),
+                _ if arg.starts_with( '-' ) =>
+                {
+                    return Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "Unknown option '{arg}'." ) ) );
+                }
+
+                _ => parsed.files.push( arg.clone() )
+            }
+        }
+
+        Ok( parsed )
+    }
+
+    fn maps_dir(&self) -> PathBuf
+    {
+        self.maps_dir.clone().unwrap_or_else( || current_dir_path!() )
+    }
+    /// Gets the single input file, erroring if there isn't exactly one.
+    fn single_file(&self, command: &str) -> io::Result<&str>
+    {
+        match self.files.as_slice()
+        {
+            [file] => Ok( file ),
+            [] => Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "'{command}' needs an input file." ) ) ),
+            _ => Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "'{command}' takes only one input file." ) ) )
+        }
+    }
+}
+/// Checks if the first argument names a subcommand, as opposed to a file dropped onto the executable.
+pub fn is_command(arg: &str) -> bool
+{
+    COMMANDS.contains( &arg ) || arg == "--help" || arg == "-h"
+}
+/// Reads the input file, or stdin if the file is "-".
+fn read_input(file: &str) -> io::Result<String>
+{
+    if file == "-"
+    {
+        let mut content = String::new();
+        io::stdin().read_to_string( &mut content )?;
+
+        return Ok( content );
+    }
+
+    fs::read_to_string( file )
+}
+/// Prints each file's outcome, erroring if any of them failed.
+fn print_report(kind: &str, report: &Report) -> io::Result<()>
+{
+    for file in &report.files
+    {
+        println!( "{file}" );
+    }
+
+    println!( "Processed {} {kind} file(s).", report.succeeded() );
+
+    if report.has_failures()
+    {
+        return Err( io::Error::other( format!( "{} {kind} file(s) failed.", report.failures().count() ) ) );
+    }
+
+    Ok( () )
+}
+/// Prints a check as Markdown or JSON, going by --format.
+fn print_formatted(args: &Args, markdown: impl FnOnce() -> String, json: impl FnOnce() -> serde_json::Result<String>) -> io::Result<()>
+{
+    match args.format.as_deref().unwrap_or( "md" ).to_ascii_lowercase().as_str()
+    {
+        "md" | "markdown" => print!( "{}", markdown() ),
+        "json" => println!( "{}", json()? ),
+        format => return Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "Unknown format '{format}', expected 'md' or 'json'." ) ) )
+    }
+
+    Ok( () )
+}
+/// Output name for a converted file, from --output or the input's stem.
+fn output_name(args: &Args, file: &str) -> String
+{
+    args.output.clone().unwrap_or_else( ||
+    {
+        Path::new( file )
+            .file_stem()
+            .and_then( |s| s.to_str() )
+            .unwrap_or( "output" )
+        .to_owned()
+    })
+}
+
+fn run_cfg(args: &Args) -> io::Result<()>
+{
+    let writetype = args.write_type.unwrap_or( WriteType::OVERWRITE );
+
+    let cvars =
+    if matches!( writetype, WriteType::DELETE | WriteType::MINIMIZE )
+    {
+        String::new()
+    }
+    else
+    {
+        read_input( args.single_file( "cfg" )? )?.trim().to_owned()
+    };
+
+    let cfg = Cfg
+    {
+        cvars,
+        writetype,
+        is_skillcfg: args.skill,
+        bspdir: args.maps_dir(),
+        bspwhitelist: args.bsps.clone()
+    };
+
+    write_cfg( args, &cfg )
+}
+/// Writes the cfgs, or prints what would change with --dry-run.
+fn write_cfg(args: &Args, cfg: &Cfg) -> io::Result<()>
+{
+    if args.dry_run
+    {
+        let changes = cfg.preview()?;
+
+        for change in &changes
+        {
+            print!( "{}", change.unified_diff() );
+        }
+
+        println!( "{} of {} CFG file(s) would change.", changes.iter().filter( |c| c.is_change() ).count(), changes.len() );
+        return Ok( () );
+    }
+
+    let report = if args.all_or_nothing { cfg.create_all_or_nothing()? } else { cfg.create()? };
+
+    print_report( "CFG", &report )
+}
+
+fn run_minimize(args: &Args) -> io::Result<()>
+{
+    let cfg = Cfg
+    {
+        cvars: String::new(),
+        writetype: WriteType::MINIMIZE,
+        is_skillcfg: args.skill,
+        bspdir: args.maps_dir(),
+        bspwhitelist: args.bsps.clone()
+    };
+
+    if !args.overrides
+    {
+        return write_cfg( args, &cfg );
+    }
+
+    for ( cfg_name, cvars ) in cfg.overrides()?
+    {
+        println!( "{}:", cfg_name.display() );
+
+        for cvar in cvars
+        {
+            println!( "  {cvar}" );
+        }
+    }
+
+    Ok( () )
+}
+
+fn run_restore() -> io::Result<()>
+{
+    let store = BackupStore::open();
+
+    if let Some( set ) = store.last()?
+    {
+        println!( "Undoing {} on {} file(s).", set.manifest.operation, set.manifest.files.len() );
+    }
+
+    print_report( "restored", &store.restore_last()? )
+}
+
+fn run_motd(args: &Args) -> io::Result<()>
+{
+    let content = read_input( args.single_file( "motd" )? )?;
+    let report = create_motd( &content, &args.maps_dir() )?;
+
+    print_report( "MOTD", &report )
+}
+
+fn run_res(args: &Args) -> io::Result<()>
+{
+    if !args.dry_run
+    {
+        return print_report( "RES", &create_res( &args.maps_dir(), &args.bsps )? );
+    }
+
+    let ( res_files, skipped ) = build_res( &args.maps_dir(), &args.bsps )?;
+
+    for ( bsp, res ) in res_files
+    {
+        match res
+        {
+            Ok( res ) => print!( "{}:\n{res}\n", res.path.display() ),
+            Err( e ) => eprintln!( "{}: {e}", bsp.display() )
+        }
+    }
+
+    for file in skipped
+    {
+        eprintln!( "{file}" );
+    }
+
+    Ok( () )
+}
+
+fn run_package(args: &Args) -> io::Result<()>
+{
+    let out_dir = args.output.as_ref().map( PathBuf::from ).unwrap_or_else( || args.maps_dir() );
+    let ( report, manifests ) = package_maps( &args.maps_dir(), &args.bsps, &out_dir )?;
+
+    for manifest in &manifests
+    {
+        println!( "{}: {} file(s)", manifest.map, manifest.files.len() );
+
+        for missing in &manifest.missing
+        {
+            println!( "  missing: {missing}" );
+        }
+    }
+
+    print_report( "package", &report )
+}
+
+fn run_replace(args: &Args) -> io::Result<()>
+{
+    if args.files.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidInput, "'replace' needs at least one .gmr/.gsr file." ) );
+    }
+
+    let mut sets = vec![];
+    let roots = GameRoots::from_config();
+
+    for file in &args.files
+    {
+        let parsed = Replacement::from_file( file.as_ref(), &roots )
+            .map_err( |e| io::Error::new( e.kind(), format!( "Could not read '{file}': {e}" ) ) )?;
+
+        for d in &parsed.diagnostics
+        {
+            println!( "{file}:{d}" );
+        }
+
+        for warning in wav::check_replacements( &parsed.replacements, &roots )
+        {
+            println!( "{file}: warning: {warning}" );
+        }
+
+        sets.push( ( output_name( args, file ), parsed.replacements ) );
+    }
+
+    if args.merge
+    {
+        let ( merged, conflicts ) = Replacement::merge( args.files.iter().zip( &sets ).map( |( file, ( _, r ) )| ( file.as_str(), r.as_slice() ) ) );
+
+        for conflict in &conflicts
+        {
+            println!( "Conflict: {conflict}" );
+        }
+
+        sets = vec![( args.output.clone().unwrap_or_else( || "merged".into() ), merged )];
+    }
+
+    let mut report = Report::default();
+
+    for ( filename, mut replacements ) in sets
+    {
+        if args.invert
+        {
+            let ( inverted, conflicts ) = Replacement::invert( &replacements );
+
+            for conflict in &conflicts
+            {
+                println!( "{filename}: conflict: {conflict}" );
+            }
+
+            replacements = inverted;
+        }
+
+        if args.collapse
+        {
+            let ( collapsed, cycles ) = Replacement::collapse( &replacements );
+
+            for cycle in &cycles
+            {
+                println!( "{filename}: cycle left as is: {} -> {}", cycle.join( " -> " ), cycle[0] );
+            }
+
+            replacements = collapsed;
+        }
+
+        let ( models, sounds ) = Replacement::partition_replacements( &replacements );
+
+        report.files.extend( [models, sounds]
+            .iter()
+            .filter( |r| !r.is_empty() )
+        .map( |r| Replacement::to_file( &filename, r ) ) );
+    }
+
+    print_report( "replacement", &report )
+}
+
+fn run_materials(args: &Args) -> io::Result<()>
+{
+    let file = args.single_file( "materials" )?;
+    let entries = MaterialEntry::from_file( file.as_ref() )
+        .ok_or_else( || io::Error::new( io::ErrorKind::InvalidData, format!( "No valid material entries in '{file}'." ) ) )?;
+
+    let filename = output_name( args, file );
+
+    print_report( "materials", &MaterialEntry::to_file( &filename, &entries ).into() )
+}
+
+fn run_wad(args: &Args) -> io::Result<()>
+{
+    if args.files.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidInput, "'wad' needs at least one .wad file." ) );
+    }
+
+    for file in &args.files
+    {
+        for name in read_texture_names( file )?
+        {
+            println!( "{name}" );
+        }
+    }
+
+    Ok( () )
+}
+
+fn run_lint(args: &Args) -> io::Result<()>
+{
+    if args.files.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidInput, "'lint' needs at least one .cfg file." ) );
+    }
+
+    let mut errors = 0;
+
+    for file in &args.files
+    {
+        let diagnostics = lint::lint( &read_input( file )?, schema() );
+
+        for d in &diagnostics
+        {
+            println!( "{file}:{d}" );
+        }
+        // Warnings are printed but don't fail the run, so a missing CVar list doesn't fail every check
+        errors += diagnostics.iter().filter( |d| d.severity == lint::Severity::Error ).count();
+    }
+
+    if errors > 0
+    {
+        return Err( io::Error::other( format!( "{errors} error(s) found." ) ) );
+    }
+
+    Ok( () )
+}
+
+fn run_audit(args: &Args) -> io::Result<()>
+{
+    let audit = Audit::run( &args.maps_dir() )?;
+
+    print_formatted( args, || audit.to_markdown(), || audit.to_json() )
+}
+
+fn run_check(args: &Args) -> io::Result<()>
+{
+    let check = ResourceCheck::run( &args.maps_dir(), &args.bsps )?;
+
+    print_formatted( args, || check.to_markdown(), || check.to_json() )?;
+    // A BSP that can't be read has an unknown list of files, so it fails the check too
+    match ( check.missing_count(), check.maps.iter().filter( |m| m.error.is_some() ).count() )
+    {
+        ( 0, 0 ) => Ok( () ),
+        ( missing, 0 ) => Err( io::Error::other( format!( "{missing} missing file(s)." ) ) ),
+        ( missing, unreadable ) => Err( io::Error::other( format!( "{missing} missing file(s), {unreadable} unreadable BSP(s)." ) ) )
+    }
+}
+
+fn run_case(args: &Args) -> io::Result<()>
+{
+    let check = CaseCheck::run( &args.maps_dir(), &args.bsps )?;
+
+    print_formatted( args, || check.to_markdown(), || check.to_json() )?;
+
+    if args.fix && !check.issues.is_empty()
+    {
+        return print_report( "fixed", &check.fix()? );
+    }
+
+    match check.issues.len()
+    {
+        0 => Ok( () ),
+        issues => Err( io::Error::other( format!( "{issues} reference(s) differ by case, run with --fix to correct them." ) ) )
+    }
+}
+
+fn run_bsp(args: &Args) -> io::Result<()>
+{
+    if args.files.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidInput, "'bsp' needs at least one .bsp file." ) );
+    }
+
+    let mut invalid = 0;
+
+    for file in &args.files
+    {
+        let bsp =
+        match Bsp::open( Path::new( file ) )
+        {
+            Ok( bsp ) => bsp,
+            Err( e ) =>
+            {
+                println!( "{file}: {e}" );
+                invalid += 1;
+                continue;
+            }
+        };
+
+        println!( "{file}: version {}, {} entities", bsp.header.version, bsp.entities.len() );
+        println!( "  wad: {}", bsp.wads().join( ";" ) );
+        println!( "  skyname: {}", bsp.skyname().unwrap_or_default() );
+        println!( "  message: {}", bsp.message().unwrap_or_default() );
+    }
+
+    if invalid > 0
+    {
+        return Err( io::Error::other( format!( "{invalid} invalid BSP(s)." ) ) );
+    }
+
+    Ok( () )
+}
+/// Runs a subcommand headlessly. Errors are printed to stderr instead of shown in a dialog.
+pub fn run(args: &[String]) -> ExitCode
+{
+    let Some( ( command, rest ) ) = args.split_first()
+    else
+    {
+        println!( "{}", usage() );
+        return ExitCode::SUCCESS;
+    };
+
+    let result = Args::parse( rest ).and_then( |args|
+    match command.as_str()
+    {
+        "cfg" => run_cfg( &args ),
+        "minimize" => run_minimize( &args ),
+        "restore" => run_restore(),
+        "motd" => run_motd( &args ),
+        "res" => run_res( &args ),
+        "package" => run_package( &args ),
+        "replace" => run_replace( &args ),
+        "materials" => run_materials( &args ),
+        "wad" => run_wad( &args ),
+        "lint" => run_lint( &args ),
+        "audit" => run_audit( &args ),
+        "check" => run_check( &args ),
+        "case" => run_case( &args ),
+        "bsp" => run_bsp( &args ),
+        _ =>
+        {
+            println!( "{}", usage() );
+            Ok( () )
+        }
+    });
+
+    match result
+    {
+        Ok( () ) => ExitCode::SUCCESS,
+        Err( e ) =>
+        {
+            eprintln!( "{APPNAME} {command}: {e}" );
+            ExitCode::FAILURE
+        }
+    }
+}
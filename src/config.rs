@@ -43,7 +43,11 @@ use crate::
     app,
     current_dir_path,
     cvar,
-    utils::search_drives
+    utils::
+    {
+        search_drives,
+        write_atomic
+    }
 };
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
@@ -53,7 +57,10 @@ pub struct Config
 {
     pub svencoopdir: Option<PathBuf>,
     #[serde( default )]
-    pub additional_cvars: Vec<String>
+    pub additional_cvars: Vec<String>,
+    /// Number of backup sets to keep, the oldest are deleted first.
+    #[serde( default, skip_serializing_if = "Option::is_none" )]
+    pub backup_limit: Option<usize>
 }
 
 impl Config
@@ -80,13 +87,7 @@ impl Config
     {
         fs::create_dir_all( appdata_base() )?;
 
-        let p = config_path();
-        let tmp = p.with_extension( "json.tmp" );
-
-        fs::write( &tmp, serde_json::to_string_pretty( &self ).map_err( io::Error::other )?.as_bytes() )?;
-        fs::rename( &tmp, &p )?;
-        
-        Ok( () )
+        write_atomic( &config_path(), serde_json::to_string_pretty( &self ).map_err( io::Error::other )? )
     }
 
     fn normalize(&mut self)
@@ -100,7 +101,7 @@ impl Config
 
 }
 
-fn appdata_base() -> PathBuf 
+pub(crate) fn appdata_base() -> PathBuf 
 {
     #[cfg( target_os = "windows" )]
     {
@@ -224,6 +225,24 @@ pub fn init() -> io::Result<PathBuf>
     }
 }
 
+/// Loads the stored config without any setup prompts, for headless runs.
+/// Falls back to the executable's folder when no Sven Co-op folder is stored.
+pub fn load() -> io::Result<()>
+{
+    let mut store = Config::read_store()?;
+    store.normalize();
+
+    let exe_path = current_dir_path!();
+    if store.svencoopdir.is_none()
+    && exe_path.join( cvar::DEFAULT_MAP_SETTINGS ).try_exists().unwrap_or( false )
+    {
+        store.svencoopdir = Some( exe_path );
+    }
+
+    let _ = CONFIG.set( store );
+    Ok( () )
+}
+
 fn save_and_return( dir: PathBuf ) -> io::Result<PathBuf>
 {
     let store = Config { svencoopdir: Some( dir.clone() ), ..Default::default() };
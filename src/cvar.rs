@@ -19,13 +19,7 @@ use std::
 {
     collections::HashSet,
     fs,
-    io::
-    {
-        self,
-        BufRead,
-        BufReader,
-        Write
-    },
+    io,
     path::
     {
         Path,
@@ -34,18 +28,40 @@ use std::
     sync::OnceLock
 };
 
-use rfd::
+use strum::
 {
-    MessageLevel,
-    MessageButtons
+    Display,
+    EnumString
 };
 
 use crate::
 {
-    app::popup,
+    backup::BackupStore,
+    bsp::BspHeader,
+    cfgdoc::
+    {
+        CfgDocument,
+        Cvar,
+        Encoding,
+        Entry,
+        read_text
+    },
     config::Config,
     current_dir_path,
-    utils::HasExtension
+    diff::FileChange,
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
+    schema::schema,
+    transaction::Transaction,
+    utils::
+    {
+        HasExtension,
+        write_atomic
+    }
 };
 
 const EXT_CFG: &str = "cfg";
@@ -114,62 +130,115 @@ static OTHER_CVARS: [&str; 50] =
     "mp_classic_mode 0"
 ];
 
+#[derive( Clone, Copy, PartialEq, Display, EnumString )]
+#[strum( ascii_case_insensitive, serialize_all = "lowercase" )]
 pub enum WriteType
 {
     OVERWRITE,
     APPEND,
     REMOVE,
-    DELETE
+    DELETE,
+    SET,
+    UNSET,
+    MERGE,
+    MINIMIZE
 }
 
 impl WriteType
 {
-    /// Executes the write operation on the given CFG file.
+    /// Works out the content of a CFG file after the write operation, without touching the file.
+    /// `before` is `None` if the file doesn't exist, and `None` is returned if it won't exist afterwards.
     ///
     /// | Variant    | Behavior |
     /// |------------|----------|
     /// | OVERWRITE  | Creates/overwrites `path` with `content` |
     /// | APPEND     | Appends `content` to `path` |
-    /// | REMOVE     | Removes lines matching `content` from `path` |
+    /// | REMOVE     | Removes CVars set to the same value as in `content` from `path` |
     /// | DELETE     | Deletes `path` (content ignored) |
-    pub(crate) fn execute(&self, path: &Path, content: &str) -> io::Result<()>
+    /// | SET        | Changes the value of CVars already in `path`, adds the rest |
+    /// | UNSET      | Removes CVars from `path` by name, whatever their value |
+    /// | MERGE      | Adds CVars missing from `path`, keeping the values of ones already set |
+    /// | MINIMIZE   | Removes CVars from `path` that end up with the same value as in `content` (the defaults) |
+    pub(crate) fn apply(&self, before: Option<&str>, content: &str) -> Option<String>
     {
         match self
         {
-            WriteType::OVERWRITE => fs::File::create( path )?.write_all( content.as_bytes() ),
-            WriteType::APPEND => 
+            WriteType::OVERWRITE => Some( content.to_owned() ),
+            WriteType::APPEND =>
             {
-                fs::OpenOptions::new()
-                    .append( true )
-                    .create( true )
-                    .open( path )?
-                .write_all( content.as_bytes() )
+                let mut doc = CfgDocument::parse( before.unwrap_or_default() );
+                doc.append( CfgDocument::parse( content ) );
+
+                Some( doc.to_string() )
             }
 
             WriteType::REMOVE =>
             {
-                let buf = fs::read_to_string( path )?;
-                let remove_lines: HashSet<_> = content.lines().collect();
-                let result: String = buf
-                    .lines()
-                    .filter( |line| !remove_lines.contains( *line ) )
-                    .collect::<Vec<_>>()
-                .join( "\n" );
-
-                fs::write( path, format!( "{result}\n" ) )
+                let remove = CfgDocument::parse( content );
+                let mut doc = CfgDocument::parse( before? );
+                doc.retain_cvars( |cvar| !remove.cvars().any( |r| r.same_setting( cvar ) ) );
+
+                Some( doc.to_string() )
+            }
+
+            WriteType::DELETE => None,
+            WriteType::SET =>
+            {
+                let mut doc = CfgDocument::parse( before.unwrap_or_default() );
+                CfgDocument::parse( content ).cvars().for_each( |cvar| doc.set( cvar.clone() ) );
+
+                Some( doc.to_string() )
+            }
+
+            WriteType::UNSET =>
+            {
+                let mut doc = CfgDocument::parse( before? );
+                CfgDocument::parse( content ).cvars().for_each( |cvar| { doc.remove( &cvar.name ); } );
+
+                Some( doc.to_string() )
             }
 
-            WriteType::DELETE =>
+            WriteType::MERGE =>
             {
-                if path.try_exists()?
+                let mut doc = CfgDocument::parse( before.unwrap_or_default() );
+
+                for cvar in CfgDocument::parse( content ).cvars()
                 {
-                    fs::remove_file( path )?;
+                    if doc.get( &cvar.name ).is_none()
+                    {
+                        doc.push( Entry::Cvar( cvar.clone() ) );
+                    }
                 }
 
-                Ok( () )
+                Some( doc.to_string() )
+            }
+
+            WriteType::MINIMIZE =>
+            {
+                let mut doc = CfgDocument::parse( before? );
+                doc.minimize( &CfgDocument::parse( content ) );
+
+                Some( doc.to_string() )
             }
         }
     }
+    /// Reads the CFG file and works out what the write operation would change, without writing anything.
+    pub(crate) fn preview(&self, path: &Path, content: &str) -> io::Result<FileChange>
+    {
+        let ( before, encoding ) =
+        if path.try_exists()?
+        {
+            let ( text, encoding ) = read_text( path )?;
+            ( Some( text ), encoding )
+        }
+        else
+        {
+            ( None, Encoding::Utf8 )
+        };
+
+        let after = self.apply( before.as_deref(), content );
+        Ok( FileChange { path: path.to_path_buf(), before, after, encoding } )
+    }
 }
 
 pub struct Cfg
@@ -182,119 +251,260 @@ pub struct Cfg
 }
 
 impl Cfg
-{   /// Creates/Modifies/Deletes cfg files based on the Cfg struct data, returns number of files processed, -1 on error
-    pub fn create(&self) -> i8
+{   /// Creates/Modifies/Deletes cfg files based on the Cfg struct data.
+    /// The files are backed up first so the operation can be undone with `BackupStore::restore_last`.
+    /// Returns an error if there is nothing to do, otherwise a report of each cfg file processed.
+    pub fn create(&self) -> io::Result<Report>
     {
-        let writetype = &self.writetype;
-        let whitelist = &self.bspwhitelist;
+        self.create_with_backup( &BackupStore::open(), false )
+    }
+    /// Same as `create`, but if any file fails to be written every other file is put back as it was.
+    pub fn create_all_or_nothing(&self) -> io::Result<Report>
+    {
+        self.create_with_backup( &BackupStore::open(), true )
+    }
+    /// Same as `create`, backing the files up into the given store.
+    pub fn create_with_backup(&self, store: &BackupStore, all_or_nothing: bool) -> io::Result<Report>
+    {
+        let content = self.content()?;
+        let ( targets, skipped ) = self.targets()?;
+        let mut changes = vec![];
+        let mut failed = vec![];
 
-        if self.cvars.is_empty() && !matches!( writetype, WriteType::DELETE )
+        for cfg_name in targets
         {
-            popup( "No CVars specified", 
-                "You did not add in any CVars.\nEnter your CVars in the text box and try again.", 
-                MessageLevel::Warning, MessageButtons::Ok );
-
-            return -1;
+            match self.writetype.preview( &cfg_name, &content )
+            {
+                Ok( change ) => changes.push( change ),
+                Err( e ) if !all_or_nothing => failed.push( FileReport::new( cfg_name, Outcome::Failed( e ) ) ),
+                Err( e ) => return Err( e )
+            }
         }
 
-        let bsps = load_bsps( self.bspdir.as_path() );
+        let mut report = self.apply_with_backup( changes, store, all_or_nothing )?;
+        report.extend( failed );
+        report.extend( skipped );
 
-        if bsps.is_empty()
+        Ok( report )
+    }
+    /// Writes the changes from `preview`, so what gets written is exactly what was shown.
+    /// A file edited since the preview is left alone.
+    pub fn apply(&self, changes: Vec<FileChange>, all_or_nothing: bool) -> io::Result<Report>
+    {
+        self.apply_with_backup( changes, &BackupStore::open(), all_or_nothing )
+    }
+    /// Same as `apply`, backing the files up into the given store.
+    /// Only the files that change are backed up, and no backup is kept if nothing was written.
+    pub fn apply_with_backup(&self, changes: Vec<FileChange>, store: &BackupStore, all_or_nothing: bool) -> io::Result<Report>
+    {
+        let ( changes, unchanged ): ( Vec<_>, Vec<_> ) = changes.into_iter().partition( FileChange::is_change );
+
+        let mut report: Report = unchanged
+            .into_iter()
+            .map( |c| FileReport::new( c.path, Outcome::Skipped( "Already up to date".into() ) ) )
+        .collect();
+
+        if changes.is_empty()
         {
-            popup( "No BSP files found", 
-                "No BSP files found.\n\nPlease place the app executable in a map folder with valid BSPs and try again.", 
-                MessageLevel::Warning, MessageButtons::Ok );
-            
-            return -1;
+            return Ok( report );
         }
-        // If whitelist is not empty, filter BSPs
-        let whitelist_stems: HashSet<_> = whitelist
-            .iter()
-            .filter_map( |w| Path::new( w ).file_stem()?.to_str() )
-            .map( |s| s.to_ascii_lowercase() )
-        .collect();
 
-        let bsps =
-        if whitelist_stems.is_empty()
+        let paths: Vec<_> = changes.iter().map( |c| c.path.clone() ).collect();
+        let set = store.snapshot( &format!( "{} CFG", self.writetype ), &paths )?;
+
+        let result =
+        if all_or_nothing
         {
-            bsps
+            changes.iter().try_for_each( FileChange::verify ).and_then( |()| Transaction::new( changes ).commit() )
         }
         else
         {
-            bsps
+            Ok( changes
                 .into_iter()
-                .filter( |path| path.file_stem()
-                .and_then( |s| s.to_str() )
-                .is_some_and( |s| whitelist_stems.contains( &s.to_ascii_lowercase() ) ) )
-            .collect()
+                .map( |change|
+                {
+                    let result = change.verify().and_then( |()| change.write() );
+                    FileReport::from_result( change.path, result )
+                })
+            .collect() )
         };
 
-        if bsps.is_empty()// But why is it empty?
+        match result
         {
-            popup( "No matching BSP files found", 
-            "No matching BSP files found from the whitelist.\n\n
-                Please adjust the whitelist or place the app executable in a map folder with valid BSPs and try again.", 
-            MessageLevel::Warning, MessageButtons::Ok );
+            Ok( written ) =>
+            {
+                store.keep( set, &written )?;
+                report.extend( written.files );
 
-            return -1;
+                Ok( report )
+            }
+            // Nothing was changed, so there is nothing to undo
+            Err( e ) =>
+            {
+                let _ = set.discard();
+                Err( e )
+            }
         }
+    }
+    /// Dry run of `create`: works out the before and after content of each cfg file without writing anything.
+    pub fn preview(&self) -> io::Result<Vec<FileChange>>
+    {
+        let content = self.content()?;
 
-        let mut count = 0;
+        self.targets()?
+            .0
+            .iter()
+            .map( |cfg_name| self.writetype.preview( cfg_name, &content ) )
+        .collect()
+    }
+    /// Lists the CVars each existing cfg file sets to something other than the server defaults.
+    pub fn overrides(&self) -> io::Result<Vec<(PathBuf, Vec<Cvar>)>>
+    {
+        let defaults = default_settings( self.is_skillcfg )?;
+        let mut overrides = vec![];
 
-        for file_path in bsps
+        for cfg_name in self.targets()?.0
         {
-            let mut cfg_name = file_path.clone();
-
-            if self.is_skillcfg
-            {
-                if let Some( stem ) = cfg_name.file_stem()
-                {
-                    let mut stem = stem.to_string_lossy().to_string();
-                    stem.push_str( "_skl.cfg" );
-                    cfg_name.set_file_name( stem );
-                }
-            }
-            else
+            if !cfg_name.try_exists()?
             {
-                cfg_name.set_extension( EXT_CFG );
+                continue;
             }
 
-            count += writetype.execute( &cfg_name, &format!( "{}\n", self.cvars ) ).is_ok() as u8;
+            let doc = CfgDocument::from_file( &cfg_name )?;
+            let cvars = doc.overrides( &defaults ).into_iter().cloned().collect();
+
+            overrides.push( ( cfg_name, cvars ) );
         }
 
-        match count
+        Ok( overrides )
+    }
+    /// Content to write, minimizing compares against the server defaults instead of the input CVars.
+    fn content(&self) -> io::Result<String>
+    {
+        match self.writetype
         {
-            0 => 
-            {
-                popup( "No CFG files written", 
-                    "No CFG files written.\n\n
-                    Please place the app executable in a map folder with valid BSPs and try again.", 
-                    MessageLevel::Warning, MessageButtons::Ok );
-            }
+            WriteType::MINIMIZE => Ok( default_settings( self.is_skillcfg )?.to_string() ),
+            _ => Ok( format!( "{}\n", self.cvars ) )
+        }
+    }
+    /// Checks the CVar values against the schema before anything gets written.
+    /// Removing CVars by name does not need the values to be valid.
+    fn validate(&self) -> io::Result<()>
+    {
+        if matches!( self.writetype, WriteType::DELETE | WriteType::UNSET | WriteType::MINIMIZE )
+        {
+            return Ok( () );
+        }
+
+        let problems: Vec<_> = CfgDocument::parse( &self.cvars )
+            .cvars()
+            .filter_map( |cvar| schema().validate( cvar ).err() )
+        .collect();
+
+        if problems.is_empty()
+        {
+            return Ok( () );
+        }
+
+        Err( io::Error::new( io::ErrorKind::InvalidInput, 
+            format!( "Invalid CVar values:\n{}\n\nFix these and try again.", problems.join( "\n" ) ) ) )
+    }
+    /// Gets the cfg files to process, from the BSPs in the folder that pass the whitelist,
+    /// along with reports of the BSPs skipped for not being valid maps.
+    fn targets(&self) -> io::Result<(Vec<PathBuf>, Vec<FileReport>)>
+    {
+        if self.cvars.is_empty() && !matches!( self.writetype, WriteType::DELETE | WriteType::MINIMIZE )
+        {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput, 
+                "You did not add in any CVars.\nEnter your CVars and try again." ) );
+        }
+
+        self.validate()?;
 
-            _ =>
+        let bsps = load_bsps( self.bspdir.as_path() );
+
+        if bsps.is_empty()
+        {
+            return Err( io::Error::new( io::ErrorKind::NotFound, 
+                "No BSP files found.\n\nPlease place the app executable in a map folder with valid BSPs and try again." ) );
+        }
+
+        let bsps = filter_bsps( bsps, &self.bspwhitelist );
+
+        if bsps.is_empty()// But why is it empty?
+        {
+            return Err( io::Error::new( io::ErrorKind::NotFound, 
+                "No matching BSP files found from the whitelist.\n\n\
+                Please adjust the whitelist or place the app executable in a map folder with valid BSPs and try again." ) );
+        }
+
+        let ( bsps, skipped ) = check_maps( bsps );
+
+        Ok( ( bsps.iter().map( |bsp| self.cfg_path( bsp ) ).collect(), skipped ) )
+    }
+    /// Gets the cfg path that belongs to a BSP.
+    fn cfg_path(&self, bsp: &Path) -> PathBuf
+    {
+        let mut cfg_name = bsp.to_path_buf();
+
+        if self.is_skillcfg
+        {
+            if let Some( stem ) = cfg_name.file_stem()
             {
-                popup( "Done", 
-                    &format!( "Processed {count} CFG file(s)." ), 
-                    MessageLevel::Info, MessageButtons::Ok );
+                let mut stem = stem.to_string_lossy().to_string();
+                stem.push_str( "_skl.cfg" );
+                cfg_name.set_file_name( stem );
             }
         }
+        else
+        {
+            cfg_name.set_extension( EXT_CFG );
+        }
+
+        cfg_name
+    }
+}
+/// Duplicates a MOTD for each BSP file in a given directory, as `<map>_motd.txt`.
+pub fn create_motd(motd_content: &str, bsp_dir: &Path) -> io::Result<Report>
+{
+    if motd_content.trim().is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidData, "MOTD content cannot be empty." ) );
+    }
+
+    let bsps = load_bsps( bsp_dir );
 
-        count as i8
+    if bsps.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::NotFound, 
+            "No BSP files found.\n\nPlease place the app executable in a map folder with valid BSPs and try again." ) );
     }
+
+    let ( bsps, skipped ) = check_maps( bsps );
+
+    let mut report: Report = bsps
+        .iter()
+        .filter_map( |bsp| bsp.file_stem().and_then( |s| s.to_str() ).map( |base| bsp.with_file_name( format!( "{base}_motd.txt" ) ) ) )
+        .map( |motd|
+        {
+            let result = write_atomic( &motd, motd_content ).map( |()| Outcome::Written );
+            FileReport::from_result( motd, result )
+        })
+    .collect();
+
+    report.extend( skipped );
+    Ok( report )
 }
-/// Reads CVars from a CFG file
-pub(crate) fn parse_cfg(file_cvars: fs::File) -> Vec<String>
+/// Reads the CVar lines from a CFG file, sorted for use as presets.
+fn read_cvar_lines(cvar_path: &Path) -> io::Result<Vec<String>>
 {
-    let mut cvars: Vec<_> = BufReader::new( file_cvars )
-        .lines()
-        .map_while( Result::ok )// fingers crossed
-        .map( |line| line.trim().to_string() )
-        .filter( |line| !line.is_empty() && !line.starts_with( "//" ) && !line.starts_with( '#' ) )
+    let mut cvars: Vec<_> = CfgDocument::from_file( cvar_path )?
+        .cvars()
+        .map( |c| Cvar { comment: None, ..c.clone() }.to_string() )
     .collect();
 
     cvars.sort();
-    cvars
+    Ok( cvars )
 }
 
 pub fn get_default_cvars() -> &'static Vec<String>
@@ -307,9 +517,9 @@ pub fn get_default_cvars() -> &'static Vec<String>
         .join( DEFAULT_MAP_SETTINGS );
 
         let mut cvars =
-        match fs::File::open( &cvar_path )
+        match read_cvar_lines( &cvar_path )
         {
-            Ok( file ) => parse_cfg( file ),
+            Ok( cvars ) => cvars,
             Err( e ) =>
             {
                 eprintln!( "Failed to load default cvars from {}: {e}", cvar_path.display() );
@@ -334,9 +544,9 @@ pub fn get_skill_cvars() -> &'static Vec<String>
             .unwrap_or_default()
         .join( SKILL_SETTINGS );
 
-        match fs::File::open( &cvar_path )
+        match read_cvar_lines( &cvar_path )
         {
-            Ok( file ) => parse_cfg( file ),
+            Ok( cvars ) => cvars,
             Err( e ) =>
             {
                 eprintln!( "Failed to load skill cvars from {}: {e}", cvar_path.display() );
@@ -345,6 +555,62 @@ pub fn get_skill_cvars() -> &'static Vec<String>
         }
     })
 }
+/// Reads the server's default settings, from skill.cfg for skill cfgs or default_map_settings.cfg otherwise.
+pub fn default_settings(is_skillcfg: bool) -> io::Result<CfgDocument>
+{
+    let settings = if is_skillcfg { SKILL_SETTINGS } else { DEFAULT_MAP_SETTINGS };
+
+    let path = Config::get().svencoopdir
+        .clone()
+        .unwrap_or_default()
+    .join( settings );
+
+    CfgDocument::from_file( &path ).map_err( |e| io::Error::new( e.kind(), 
+        format!( "Failed to read the default settings from {}: {e}", path.display() ) ) )
+}
+/// Keeps the BSPs named in the whitelist, or all of them if it is empty. Names are matched without extension or case.
+pub fn filter_bsps(bsps: Vec<PathBuf>, whitelist: &[String]) -> Vec<PathBuf>
+{
+    let whitelist_stems: HashSet<_> = whitelist
+        .iter()
+        .filter_map( |w| Path::new( w ).file_stem()?.to_str() )
+        .map( |s| s.to_ascii_lowercase() )
+    .collect();
+
+    if whitelist_stems.is_empty()
+    {
+        return bsps;
+    }
+
+    bsps
+        .into_iter()
+        .filter( |path| path.file_stem()
+        .and_then( |s| s.to_str() )
+        .is_some_and( |s| whitelist_stems.contains( &s.to_ascii_lowercase() ) ) )
+    .collect()
+}
+/// Splits BSP files into the valid BSP v30 maps and reports of the files skipped for not being one, saying why.
+/// Truncated files and maps for other games still have the `.bsp` extension, but get nothing written for them.
+pub fn check_maps(bsps: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<FileReport>)
+{
+    let mut skipped = vec![];
+
+    let maps = bsps
+        .into_iter()
+        .filter( |bsp|
+        match BspHeader::read( bsp )
+        {
+            Ok( _ ) => true,
+            Err( e ) =>
+            {
+                skipped.push( FileReport::new( bsp, Outcome::Skipped( format!( "Not a valid map: {e}" ) ) ) );
+                false
+            }
+        })
+    .collect();
+
+    ( maps, skipped )
+}
 /// Collects all BSP files in a given directory and returns their paths.
 pub fn load_bsps(chosen_path: &Path) -> Vec<PathBuf>
 {   // Use the chosen_dir if it exists, otherwise fall back to current_dir
@@ -0,0 +1,226 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fs,
+    io,
+    path::PathBuf
+};
+
+use crate::
+{
+    cfgdoc::
+    {
+        Encoding,
+        read_text
+    },
+    report::Outcome,
+    utils::write_atomic
+};
+
+const CONTEXT_LINES: usize = 3;
+
+#[derive( Clone, Copy, PartialEq )]
+enum Op<'a>
+{
+    Equal( &'a str ),
+    Delete( &'a str ),
+    Insert( &'a str )
+}
+/// Before and after content of a file that an operation would touch.
+/// `None` means the file does not exist (before) or will be deleted (after).
+pub struct FileChange
+{
+    pub path: PathBuf,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    /// Encoding the file is written in, the one it was read with.
+    pub encoding: Encoding
+}
+
+impl FileChange
+{
+    pub fn is_change(&self) -> bool
+    {
+        self.before != self.after
+    }
+    /// Checks the file still has the content the change was worked out from, so edits made since are not overwritten.
+    pub fn verify(&self) -> io::Result<()>
+    {
+        let current = if self.path.try_exists()? { Some( read_text( &self.path )?.0 ) } else { None };
+
+        if current != self.before
+        {
+            return Err( io::Error::other( format!( "{} changed since the preview, preview it again.", self.path.display() ) ) );
+        }
+
+        Ok( () )
+    }
+    /// Writes the new content in the encoding the file was read with, or deletes the file if it goes away.
+    pub fn write(&self) -> io::Result<Outcome>
+    {
+        match ( &self.before, &self.after )
+        {
+            ( None, None ) => Ok( Outcome::Skipped( "No file to change".into() ) ),
+            ( Some( _ ), None ) => fs::remove_file( &self.path ).map( |()| Outcome::Deleted ),
+            ( _, Some( after ) ) => write_atomic( &self.path, self.encoding.encode( after )? ).map( |()| Outcome::Written )
+        }
+    }
+    /// Renders the change as a unified diff, empty if nothing changes.
+    pub fn unified_diff(&self) -> String
+    {
+        if !self.is_change()
+        {
+            return String::new();
+        }
+
+        let path = self.path.display().to_string();
+        let old_name = if self.before.is_some() { path.as_str() } else { "/dev/null" };
+        let new_name = if self.after.is_some() { path.as_str() } else { "/dev/null" };
+
+        unified_diff( old_name, new_name, self.before.as_deref().unwrap_or( "" ), self.after.as_deref().unwrap_or( "" ) )
+    }
+}
+/// Line diff from the longest common subsequence of both files.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>>
+{
+    let ( n, m ) = ( old.len(), new.len() );
+    // lcs[i][j] is the LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+
+    for i in ( 0..n ).rev()
+    {
+        for j in ( 0..m ).rev()
+        {
+            lcs[i][j] =
+            if old[i] == new[j]
+            {
+                lcs[i + 1][j + 1] + 1
+            }
+            else
+            {
+                lcs[i + 1][j].max( lcs[i][j + 1] )
+            };
+        }
+    }
+
+    let ( mut i, mut j ) = ( 0, 0 );
+    let mut ops = Vec::with_capacity( n + m );
+
+    while i < n && j < m
+    {
+        if old[i] == new[j]
+        {
+            ops.push( Op::Equal( old[i] ) );
+            i += 1;
+            j += 1;
+        }
+        else if lcs[i + 1][j] >= lcs[i][j + 1]
+        {
+            ops.push( Op::Delete( old[i] ) );
+            i += 1;
+        }
+        else
+        {
+            ops.push( Op::Insert( new[j] ) );
+            j += 1;
+        }
+    }
+
+    ops.extend( old[i..].iter().map( |l| Op::Delete( l ) ) );
+    ops.extend( new[j..].iter().map( |l| Op::Insert( l ) ) );
+    ops
+}
+/// Hunk header line range, an empty range points at the line before it.
+fn hunk_range(start: usize, count: usize) -> String
+{
+    match count
+    {
+        0 => format!( "{start},0" ),
+        1 => format!( "{}", start + 1 ),
+        _ => format!( "{},{count}", start + 1 )
+    }
+}
+/// Renders a unified diff between two texts with 3 lines of context, empty if they are the same.
+/// Lines are compared with their line endings, so a change of ending alone shows up too.
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String
+{
+    let old_lines: Vec<_> = old.split_inclusive( '\n' ).collect();
+    let new_lines: Vec<_> = new.split_inclusive( '\n' ).collect();
+    let ops = diff_lines( &old_lines, &new_lines );
+
+    let changes: Vec<_> = ops
+        .iter()
+        .enumerate()
+        .filter( |( _, op )| !matches!( op, Op::Equal( _ ) ) )
+        .map( |( i, _ )| i )
+    .collect();
+
+    if changes.is_empty()
+    {
+        return String::new();
+    }
+    // Group changes that are close enough to share their context
+    let mut hunks: Vec<(usize, usize)> = vec![];
+
+    for &c in &changes
+    {
+        let start = c.saturating_sub( CONTEXT_LINES );
+        let end = ( c + CONTEXT_LINES + 1 ).min( ops.len() );
+
+        match hunks.last_mut()
+        {
+            Some( last ) if start <= last.1 => last.1 = end,
+            _ => hunks.push( ( start, end ) )
+        }
+    }
+
+    let mut out = format!( "--- {old_name}\n+++ {new_name}\n" );
+
+    for ( start, end ) in hunks
+    {
+        let before = &ops[..start];
+        let old_start = before.iter().filter( |op| !matches!( op, Op::Insert( _ ) ) ).count();
+        let new_start = before.iter().filter( |op| !matches!( op, Op::Delete( _ ) ) ).count();
+
+        let hunk = &ops[start..end];
+        let old_count = hunk.iter().filter( |op| !matches!( op, Op::Insert( _ ) ) ).count();
+        let new_count = hunk.iter().filter( |op| !matches!( op, Op::Delete( _ ) ) ).count();
+
+        out.push_str( &format!( "@@ -{} +{} @@\n", hunk_range( old_start, old_count ), hunk_range( new_start, new_count ) ) );
+
+        for op in hunk
+        {
+            let ( marker, line ) =
+            match op
+            {
+                Op::Equal( l ) => ( ' ', l ),
+                Op::Delete( l ) => ( '-', l ),
+                Op::Insert( l ) => ( '+', l )
+            };
+
+            match line.strip_suffix( '\n' )
+            {
+                Some( line ) => out.push_str( &format!( "{marker}{line}\n" ) ),
+                None => out.push_str( &format!( "{marker}{line}\n\\ No newline at end of file\n" ) )
+            }
+        }
+    }
+
+    out
+}
@@ -20,80 +20,52 @@ use std::
     env, 
     fs, 
     io, 
-    path::Path
-};
-
-use rfd::
-{
-    MessageButtons,
-    MessageLevel
+    path::Path,
+    process::ExitCode
 };
 
 use crate::
 {
     app,
+    cli,
     config,
     current_dir_path,
     prelude::*
 };
-/// Helper for duplicating a MOTD file for each BSP file in a given directory.
-fn create_motd(motd_content: String, bsp_path: &Path) -> io::Result<u8>
+fn is_reset_flag(arg: &str) -> bool
 {
-    if motd_content.trim().is_empty()
-    {
-        return Err( io::Error::new( io::ErrorKind::InvalidData, "MOTD content cannot be empty." ) );
-    }
-
-    let bsps = load_bsps( bsp_path );
-
-    if bsps.is_empty()
-    {
-        app::popup( "No BSP files found", 
-            "Please place the app executable in a map folder with valid BSPs and try again.", 
-            MessageLevel::Warning, 
-            MessageButtons::Ok );
-
-        return Ok( 0 );
-    }
-
-    let count = bsps
-        .iter()
-        .filter_map( |bsp| bsp.file_stem().and_then( |s| s.to_str() ) )
-        .map( |base| fs::write( format!( "{base}_motd.txt" ), &motd_content ).is_ok() as u8 )
-    .sum::<_>();
-
-    match count
-    {
-        0 =>
-        { 
-            app::popup( "No MOTD files written", 
-                "Please place the app executable in a map folder with valid BSPs and try again.", 
-                MessageLevel::Warning, MessageButtons::Ok );
-        }
-
-        _ =>
-        {
-            app::popup( "Done", &format!( "Processed {count} MOTD file(s)." ), 
-                MessageLevel::Info, 
-                MessageButtons::Ok );
-        }
-    }
-
-    Ok( count )
+    arg == "--reset-config" || arg == "-reset" || arg == "-r"
+}
+/// Arguments after the executable, without the config reset flags.
+fn args() -> Vec<String>
+{
+    env::args().skip( 1 ).filter( |a| !is_reset_flag( a ) ).collect()
+}
+/// Whether the app was started with a CLI subcommand, which runs without any window or dialog.
+pub fn is_headless() -> bool
+{
+    args().first().is_some_and( |command| cli::is_command( command ) )
 }
 
-pub fn run() -> io::Result<()>
+pub fn run() -> io::Result<ExitCode>
 {
-    if env::args().any( |a| a == "--reset-config" || a == "-reset" || a == "-r" )
+    if env::args().any( |a| is_reset_flag( &a ) )
     {
         config::reset()?;
     }
 
+    let args = args();
+    // Subcommands run headless, so skip the interactive first time setup
+    if is_headless()
+    {
+        config::load()?;
+        return Ok( cli::run( &args ) );
+    }
+
     config::init()?;
 
-    if let args = env::args().skip( 1 ).filter( |a| a != "--reset-config" && a != "-r" ).collect::<Vec<_>>() 
-    && !args.is_empty()
-    {
+    if !args.is_empty()
+    {   // Files dropped onto the executable
         for file in &args
         {
             if file.has_extension( &["cfg"] )
@@ -101,7 +73,7 @@ pub fn run() -> io::Result<()>
                 if let Ok( content ) = fs::read_to_string( file )
                 && !content.trim().is_empty()
                 {
-                    Cfg
+                    let result = Cfg
                     {
                         cvars: content,
                         writetype: WriteType::OVERWRITE,
@@ -109,22 +81,27 @@ pub fn run() -> io::Result<()>
                         bspdir: current_dir_path!(),
                         bspwhitelist: vec![]
                     }.create();
+
+                    app::show_report( "CFG", result );
                 }
             }
             else if file.ends_with( "_motd.txt" )
             && let Ok( content ) = fs::read_to_string( file )
             {
-                create_motd( content, &current_dir_path!() )?;
+                app::show_report( "MOTD", create_motd( &content, &current_dir_path!() ) );
             }
             else if file.has_extension( &[EXT_GMR, EXT_GSR] )
-            && let Some( replacements ) = Replacement::from_file( file.as_ref() )
+            && let Ok( parsed ) = Replacement::from_file( file.as_ref(), &GameRoots::from_config() )
             {
+                let name = Path::new( file ).file_name().unwrap_or_default().to_string_lossy();
+                app::show_diagnostics( &name, &parsed.diagnostics );
+
                 let filename = Path::new( file )
                     .file_stem()
                     .and_then( |s| s.to_str() )
                 .unwrap_or( "output" );
 
-                let ( models, sounds ) = Replacement::partition_replacements( &replacements );
+                let ( models, sounds ) = Replacement::partition_replacements( &parsed.replacements );
 
                 if !models.is_empty()
                 {
@@ -143,5 +120,5 @@ pub fn run() -> io::Result<()>
         app::launch_gui().map_err( io::Error::other )?
     }
 
-    Ok( () )
+    Ok( ExitCode::SUCCESS )
 }
@@ -0,0 +1,179 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    collections::HashMap,
+    fmt::
+    {
+        self,
+        Display
+    }
+};
+
+use crate::
+{
+    cfgdoc::
+    {
+        CfgDocument,
+        Entry
+    },
+    schema::Schema,
+    utils::edit_distance
+};
+
+#[derive( Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord )]
+pub enum Severity
+{
+    Warning,
+    Error
+}
+
+impl Display for Severity
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::Warning => write!( f, "warning" ),
+            Self::Error => write!( f, "error" )
+        }
+    }
+}
+/// A problem found on a line of an input file. Line and column start at 1.
+#[derive( Clone, Debug )]
+pub struct Diagnostic
+{
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String
+}
+
+impl Display for Diagnostic
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!( f, "{}:{}: {}: {}", self.line, self.column, self.severity, self.message )
+    }
+}
+/// Finds the closest known CVar name, if it is close enough to be a typo.
+fn suggest<'a>(name: &str, schema: &'a Schema) -> Option<&'a str>
+{
+    let max_distance = ( name.len() / 4 ).clamp( 1, 3 );
+
+    schema
+        .names()
+        .map( |k| ( edit_distance( name, k ), k ) )
+        .filter( |( d, _ )| *d <= max_distance )
+        .min()
+    .map( |( _, k )| k )
+}
+/// Checks CFG text for unknown CVars, invalid values, conflicting duplicates and malformed lines.
+pub fn lint(text: &str, schema: &Schema) -> Vec<Diagnostic>
+{
+    let doc = CfgDocument::parse( text );
+    let mut seen: HashMap<String, (usize, String)> = HashMap::new();
+    let mut diagnostics = vec![];
+    // Without the default settings most real CVars would be reported as unknown
+    if !schema.is_complete()
+    {
+        diagnostics.push( Diagnostic
+        {
+            line: 1,
+            column: 1,
+            severity: Severity::Warning,
+            message: "The default CVar list is unavailable, so unknown CVars were not checked. Set the Sven Co-op folder to check them.".into()
+        });
+    }
+
+    for ( line, column, entry ) in doc.located_entries()
+    {
+        let mut report = |severity, message: String|
+        {
+            diagnostics.push( Diagnostic { line, column, severity, message } );
+        };
+
+        let cvar =
+        match entry
+        {
+            Entry::Cvar( cvar ) => cvar,
+            Entry::Malformed( text ) =>
+            {
+                report( Severity::Error, format!( "Malformed line '{text}', expected 'name value'." ) );
+                continue;
+            }
+
+            _ => continue
+        };
+
+        let name = cvar.name.to_ascii_lowercase();
+
+        match schema.get( &name )
+        {
+            None if !schema.is_complete() => { }
+            None =>
+            {
+                let message =
+                match suggest( &name, schema )
+                {
+                    Some( suggestion ) => format!( "Unknown CVar '{}', did you mean '{suggestion}'?", cvar.name ),
+                    None => format!( "Unknown CVar '{}'.", cvar.name )
+                };
+
+                report( Severity::Error, message );
+            }
+
+            Some( spec ) if cvar.value.is_empty() =>
+            {
+                if spec.default.as_deref().is_some_and( |d| !d.is_empty() )
+                {
+                    report( Severity::Warning, format!( "CVar '{}' has no value.", cvar.name ) );
+                }
+            }
+
+            Some( spec ) =>
+            {
+                if let Err( e ) = spec.validate( &cvar.value )
+                {   // Types guessed from the defaults can be wrong, only declared ones are certain
+                    let severity = if spec.declared { Severity::Error } else { Severity::Warning };
+                    report( severity, format!( "Invalid value for CVar '{}': {e}.", cvar.name ) );
+                }
+            }
+        }
+
+        match seen.get( &name )
+        {
+            Some( ( first, value ) ) if *value != cvar.value =>
+            {
+                report( Severity::Warning, format!( "CVar '{}' was already set to '{value}' on line {first}, this line changes it to '{}'.", cvar.name, cvar.value ) );
+            }
+
+            Some( ( first, _ ) ) =>
+            {
+                report( Severity::Warning, format!( "CVar '{}' is a duplicate of line {first}.", cvar.name ) );
+            }
+
+            None =>
+            {
+                seen.insert( name, ( line, cvar.value.clone() ) );
+            }
+        }
+    }
+
+    diagnostics
+}
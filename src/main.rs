@@ -16,14 +16,32 @@ You should have received a copy of the GNU General Public License
 along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
 #![cfg_attr( target_os = "windows", windows_subsystem = "windows" )]
+mod cli;
 mod driver;
 pub mod app;
+pub mod audit;
+pub mod backup;
+pub mod bsp;
+pub mod casecheck;
+pub mod cfgdoc;
 pub mod config;
 pub mod cvar;
+pub mod diff;
+pub mod lint;
+pub mod mdl;
 pub mod replacements;
+pub mod rescheck;
 pub mod materials;
+pub mod package;
 pub mod prelude;
+pub mod report;
+pub mod resources;
+pub mod schema;
+pub mod spr;
+pub mod transaction;
 pub mod utils;
+pub mod wad;
+pub mod wav;
 #[cfg( test )] mod tests;
 
 pub const APPNAME: &str = env!( "CARGO_PKG_NAME" );
@@ -37,24 +55,54 @@ fn main() -> std::process::ExitCode
     // TODO: remove once winit supports Wayland DnD
     #[cfg( target_os = "linux" )] unsafe { std::env::remove_var( "WAYLAND_DISPLAY" ); }
 
-    match driver::run()
+    let headless = driver::is_headless();
+    // A GUI subsystem app has no console of its own, so borrow the one it was started from
+    #[cfg( target_os = "windows" )]
+    if headless
     {
-        Ok( () ) =>
+        attach_parent_console();
+    }
+
+    match driver::run()
+    {   // Subcommands print their own results, anything else here would spoil output meant for other programs
+        Ok( code ) if headless => code,
+        Ok( code ) =>
         {
-            println!( "Application ran successfully." );
-            std::process::ExitCode::SUCCESS
+            if code == std::process::ExitCode::SUCCESS
+            {
+                println!( "Application ran successfully." );
+            }
+
+            code
         }
 
         Err( e ) =>
         {
-            rfd::MessageDialog::new()
-                .set_title( "Fatal Error" )
-                .set_description( format!( "{e}" ) )
-                .set_level( rfd::MessageLevel::Error )
-            .show();
+            if !headless
+            {
+                rfd::MessageDialog::new()
+                    .set_title( "Fatal Error" )
+                    .set_description( format!( "{e}" ) )
+                    .set_level( rfd::MessageLevel::Error )
+                .show();
+            }
 
             eprintln!( "Application error: {e}" );
             std::process::ExitCode::FAILURE
         }
     }
 }
+/// Sends stdout and stderr to the console of the process that started the app, if there is one.
+#[cfg( target_os = "windows" )]
+fn attach_parent_console()
+{
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link( name = "kernel32" )]
+    unsafe extern "system"
+    {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // SAFETY: AttachConsole takes no pointers and fails harmlessly when there is no parent console.
+    unsafe { AttachConsole( ATTACH_PARENT_PROCESS ); }
+}
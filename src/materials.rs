@@ -19,14 +19,7 @@ along with this program. If not, see <https://www.gnu.org/licenses/>.
 use std::
 {
     fmt::Display, 
-    fs::File,
-    io::
-    {
-        self,
-        Read,
-        Seek,
-        SeekFrom
-    },
+    io,
     path::Path
 };
 
@@ -38,10 +31,19 @@ use strum::
     IntoEnumIterator
 };
 
-use crate::utils::
+use crate::
 {
-    read_trimmed_lines,
-    write_lines
+    report::
+    {
+        FileReport,
+        Outcome
+    },
+    utils::
+    {
+        read_trimmed_lines,
+        write_lines
+    },
+    wad::Wad
 };
 
 #[repr( u8 )]
@@ -143,9 +145,10 @@ impl MaterialEntry
         if materials.is_empty() { None } else { Some( materials ) }
     }
     /// Writes the material entries to a file in txt format
-    pub fn to_file(filename: &str, entries: &[Self]) -> io::Result<()>
+    pub fn to_file(filename: &str, entries: &[Self]) -> FileReport
     {
-        write_lines( filename, "txt", entries )
+        let result = write_lines( filename, "txt", entries ).map( |()| Outcome::Written );
+        FileReport::from_result( format!( "{filename}.txt" ), result )
     }
     /// Strips the texture identifier from the start of a texture name.
     pub fn without_tex_iden(texture_name: &str) -> String
@@ -176,40 +179,8 @@ impl Display for MaterialEntry
         }
     }
 }
-/// Reads WAD for entire list of texture names.
+/// Reads WAD for entire list of texture names, from its directory alone.
 pub fn read_texture_names(wad_path: impl AsRef<Path>) -> io::Result<Vec<String>>
 {
-    let mut file = File::open( wad_path )?;
-    let mut header = [0u8; 12];
-    file.read_exact( &mut header )?;
-
-    if &header[0..4] != b"WAD3"
-    {
-        return Err( io::Error::new( io::ErrorKind::InvalidInput, "Not a valid WAD3 file." ) );
-    }
-
-    let num_items = i32::from_le_bytes( header[4..8].try_into().map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) )? ) as usize;
-    let dir_offset = u32::from_le_bytes( header[8..12].try_into().map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) )? ) as u64;
-
-    file.seek( SeekFrom::Start( dir_offset ) )?;
-
-    let mut names = Vec::with_capacity( num_items );
-    let mut entry = [0u8; 32];
-    
-    for _ in 0..num_items
-    {
-        file.read_exact( &mut entry )?;
-        let name = entry[16..32]
-            .iter()
-            .take_while( |&&b| b != 0 )
-            .copied()
-        .collect::<Vec<_>>();
-
-        if let Ok( tex ) = String::from_utf8( name )
-        {
-            names.push( tex );
-        }
-    }
-
-    Ok( names )
+    Ok( Wad::read_directory( wad_path.as_ref() )?.into_iter().map( |l| l.name ).collect() )
 }
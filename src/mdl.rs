@@ -0,0 +1,260 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs,
+    io,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+pub const EXT_MDL: &str = "mdl";
+pub const MDL_VERSION: i32 = 10;
+const MDL_ID: &[u8; 4] = b"IDST";
+/// Id of the `<model>01.mdl` files holding sequence groups.
+const SEQ_GROUP_ID: &[u8; 4] = b"IDSQ";
+const HEADER_SIZE: usize = 244;
+const SEQUENCE_SIZE: usize = 176;
+const BODY_PART_SIZE: usize = 76;
+const ATTACHMENT_SIZE: usize = 88;
+/// Counts above this are taken as a damaged file rather than read.
+const MAX_COUNT: i32 = 4096;
+/// A body group and the number of submodels it can switch between.
+#[derive( Clone, Debug, PartialEq )]
+pub struct BodyGroup
+{
+    pub name: String,
+    pub models: usize
+}
+/// What a replacement model needs to match from the header of a GoldSrc (v10) studio model.
+#[derive( Clone, Debug )]
+pub struct Mdl
+{
+    /// Name stored in the model when it was compiled.
+    pub name: String,
+    pub sequences: Vec<String>,
+    pub body_groups: Vec<BodyGroup>,
+    /// Number of skin families, unknown when the textures are in a `<model>T.mdl` that wasn't read.
+    pub skins: Option<usize>,
+    /// Attachment names, often empty as few compilers keep them.
+    pub attachments: Vec<String>,
+    /// Textures stored in the model itself. Zero means they are in `<model>T.mdl`.
+    pub textures: usize,
+    /// Sequence groups, including the one in the model itself. The others are in `<model>01.mdl` and on.
+    pub sequence_groups: usize
+}
+
+impl Mdl
+{   /// Reads the header of a model file, and the skin families from `<model>T.mdl` when its textures are kept there.
+    pub fn read(path: &Path) -> io::Result<Self>
+    {
+        let mut mdl = Self::from_bytes( &fs::read( path )? )?;
+
+        if mdl.textures == 0
+        {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            // A missing or broken texture model is reported as a missing companion, the skins just stay unknown
+            mdl.skins = fs::read( path.with_file_name( format!( "{stem}T.mdl" ) ) )
+                .ok()
+                .and_then( |bytes| Self::from_bytes( &bytes ).ok() )
+            .and_then( |textures| textures.skins );
+        }
+
+        Ok( mdl )
+    }
+    /// Reads the header of a model from its bytes. The skins are left unknown if the textures are in `<model>T.mdl`.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self>
+    {
+        if bytes.len() < HEADER_SIZE
+        {
+            return Err( invalid( "File is too small to be a model." ) );
+        }
+
+        match &bytes[..4]
+        {
+            id if id == MDL_ID => { }
+            id if id == SEQ_GROUP_ID => return Err( invalid( "This is a sequence group file, not a model." ) ),
+            _ => return Err( invalid( "Not a GoldSrc model." ) )
+        }
+
+        let read_i32 = |at: usize| i32::from_le_bytes( [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]] );
+        let version = read_i32( 4 );
+
+        if version != MDL_VERSION
+        {
+            return Err( invalid( &format!( "Unsupported MDL version {version}, expected {MDL_VERSION}." ) ) );
+        }
+        // Where each array starts and how many entries it has, checked to lie inside the file
+        let table = |count_at: usize, index_at: usize, size: usize, what: &str| -> io::Result<(usize, usize)>
+        {
+            let ( count, index ) = ( read_i32( count_at ), read_i32( index_at ) );
+
+            if !( 0..=MAX_COUNT ).contains( &count ) || index < 0
+            || index as usize + count as usize * size > bytes.len()
+            {
+                return Err( invalid( &format!( "The {what} table is outside the file." ) ) );
+            }
+
+            Ok( ( count as usize, index as usize ) )
+        };
+
+        let ( sequence_count, sequence_index ) = table( 164, 168, SEQUENCE_SIZE, "sequence" )?;
+        let ( body_count, body_index ) = table( 204, 208, BODY_PART_SIZE, "body part" )?;
+        let ( attachment_count, attachment_index ) = table( 212, 216, ATTACHMENT_SIZE, "attachment" )?;
+
+        let sequences = ( 0..sequence_count )
+            .map( |i| c_string( &bytes[sequence_index + i * SEQUENCE_SIZE..][..32] ) )
+        .collect();
+
+        let body_groups = ( 0..body_count )
+            .map( |i|
+            {
+                let at = body_index + i * BODY_PART_SIZE;
+                BodyGroup { name: c_string( &bytes[at..at + 64] ), models: read_i32( at + 64 ).max( 0 ) as usize }
+            })
+        .collect();
+
+        let attachments = ( 0..attachment_count )
+            .map( |i| c_string( &bytes[attachment_index + i * ATTACHMENT_SIZE..][..32] ) )
+        .collect();
+
+        let textures = read_i32( 180 ).max( 0 ) as usize;
+
+        Ok( Self
+        {
+            name: c_string( &bytes[8..72] ),
+            sequences,
+            body_groups,
+            skins: ( textures > 0 ).then( || read_i32( 196 ).max( 0 ) as usize ),
+            attachments,
+            textures,
+            sequence_groups: read_i32( 172 ).max( 0 ) as usize
+        })
+    }
+    /// The extra files the game loads along with the model at `path`: `<model>T.mdl` for its textures
+    /// and `<model>01.mdl` and on for its sequence groups.
+    pub fn companions(&self, path: &Path) -> Vec<PathBuf>
+    {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut companions = vec![];
+
+        if self.textures == 0
+        {
+            companions.push( path.with_file_name( format!( "{stem}T.mdl" ) ) );
+        }
+
+        companions.extend( ( 1..self.sequence_groups ).map( |i| path.with_file_name( format!( "{stem}{i:02}.mdl" ) ) ) );
+        companions
+    }
+    /// What the new model lacks that the original has, which breaks maps and scripts that rely on it.
+    pub fn compatibility(&self, new: &Self) -> Vec<String>
+    {
+        let mut warnings = vec![];
+
+        let missing: Vec<_> = self.sequences
+            .iter()
+            .filter( |s| !new.sequences.iter().any( |n| n.eq_ignore_ascii_case( s ) ) )
+            .map( String::as_str )
+        .collect();
+
+        if !missing.is_empty()
+        {
+            warnings.push( format!( "Missing {} sequence(s): {}", missing.len(), missing.join( ", " ) ) );
+        }
+
+        for ( i, group ) in self.body_groups.iter().enumerate()
+        {
+            match new.body_groups.get( i )
+            {
+                None => warnings.push( format!( "Missing body group {i} ({})", group.name ) ),
+                Some( other ) if other.models < group.models =>
+                {
+                    warnings.push( format!( "Body group {i} ({}) has {} submodel(s), the original has {}", other.name, other.models, group.models ) );
+                }
+
+                Some( _ ) => { }
+            }
+        }
+
+        if let ( Some( original ), Some( skins ) ) = ( self.skins, new.skins ) && skins < original
+        {
+            warnings.push( format!( "Has {skins} skin(s), the original has {original}" ) );
+        }
+
+        if new.attachments.len() < self.attachments.len()
+        {
+            warnings.push( format!( "Has {} attachment(s), the original has {}", new.attachments.len(), self.attachments.len() ) );
+        }
+
+        warnings
+    }
+}
+
+impl Display for Mdl
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let skins = self.skins.map_or( "unknown".into(), |s| s.to_string() );
+
+        write!( f, "{} sequence(s), {} body group(s), {skins} skin(s), {} attachment(s)",
+            self.sequences.len(), self.body_groups.len(), self.attachments.len() )
+    }
+}
+/// Checks a model replacement for what the new model lacks compared to the original, and companion files it is missing.
+/// Files that can't be read are warned about too, as the game won't load them either.
+pub fn replacement_warnings(original: &Path, new: &Path) -> Vec<String>
+{
+    let read = |path: &Path| Mdl::read( path ).map_err( |e| format!( "Could not read {}: {e}", path.display() ) );
+
+    let ( original_mdl, new_mdl ) =
+    match ( read( original ), read( new ) )
+    {
+        ( Ok( o ), Ok( n ) ) => ( o, n ),
+        ( Err( e ), Ok( _ ) ) | ( Ok( _ ), Err( e ) ) => return vec![e],
+        ( Err( a ), Err( b ) ) => return vec![a, b]
+    };
+
+    let mut warnings = original_mdl.compatibility( &new_mdl );
+
+    warnings.extend( new_mdl
+        .companions( new )
+        .into_iter()
+        .filter( |c| !c.is_file() )
+    .map( |c| format!( "Missing companion file {}", c.display() ) ) );
+
+    warnings
+}
+/// Text of a fixed size, zero padded string field.
+fn c_string(bytes: &[u8]) -> String
+{
+    let end = bytes.iter().position( |&b| b == 0 ).unwrap_or( bytes.len() );
+    String::from_utf8_lossy( &bytes[..end] ).trim().to_owned()
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new( io::ErrorKind::InvalidData, message.to_owned() )
+}
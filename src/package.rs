@@ -0,0 +1,251 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    collections::HashSet,
+    fs::
+    {
+        self,
+        File
+    },
+    io::
+    {
+        self,
+        BufWriter,
+        Read,
+        Write
+    },
+    path::
+    {
+        Path,
+        PathBuf
+    },
+    time::
+    {
+        SystemTime,
+        UNIX_EPOCH
+    }
+};
+
+use serde::Serialize;
+use time::
+{
+    OffsetDateTime,
+    PrimitiveDateTime
+};
+use zip::
+{
+    CompressionMethod,
+    DateTime,
+    ZipWriter,
+    write::SimpleFileOptions
+};
+
+use crate::
+{
+    audit::COMPANION_SUFFIXES,
+    config::Config,
+    cvar::
+    {
+        check_maps,
+        filter_bsps,
+        load_bsps
+    },
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
+    resources::
+    {
+        ResFile,
+        content_dirs
+    },
+    utils::temp_path
+};
+
+pub const EXT_ZIP: &str = "zip";
+/// A file stored in the archive.
+#[derive( Clone, Debug, Serialize )]
+pub struct PackedFile
+{
+    /// Path inside the archive, which is the path relative to the game folder.
+    pub path: String,
+    /// Where the file was read from.
+    pub source: PathBuf,
+    pub size: u64,
+    pub crc32: String
+}
+/// What went into a map's archive, stored in it as `<map>_manifest.json`.
+#[derive( Clone, Debug, Serialize )]
+pub struct Manifest
+{
+    pub map: String,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub files: Vec<PackedFile>,
+    /// Custom resources the map needs that could not be found, so are not in the archive.
+    pub missing: Vec<String>
+}
+/// Packages a map with its companion files and the custom resources it needs into `<map>.zip` in `out_dir`.
+/// Files that ship with the game in `stock_dir` are left out. The manifest is added to the archive and returned.
+pub fn package_map(bsp_path: &Path, out_dir: &Path, stock_dir: Option<&Path>) -> io::Result<Manifest>
+{
+    let map = bsp_path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let maps_dir = bsp_path.parent().unwrap_or( Path::new( "" ) );
+    let game_dir = maps_dir.parent().unwrap_or( maps_dir );
+
+    let mut entries = vec![( format!( "maps/{}", bsp_path.file_name().unwrap_or_default().to_string_lossy() ), bsp_path.to_path_buf() )];
+    entries.extend( companions( maps_dir, &map )?.into_iter().map( |name| ( format!( "maps/{name}" ), maps_dir.join( name ) ) ) );
+
+    let mut dirs = stock_dir.and_then( Path::parent ).map( content_dirs ).unwrap_or_default();
+    dirs.retain( |d| d != game_dir );
+    dirs.insert( 0, game_dir.to_path_buf() );
+
+    let res = ResFile::build( bsp_path, stock_dir )?;
+    let mut missing: Vec<_> = res.missing.into_iter().map( |r| r.path ).collect();
+
+    for resource in res.resources
+    {
+        match dirs.iter().map( |d| d.join( &resource.path ) ).find( |p| p.is_file() )
+        {
+            Some( source ) => entries.push( ( resource.path, source ) ),
+            None => missing.push( resource.path )
+        }
+    }
+
+    let mut seen = HashSet::new();
+    entries.retain( |( path, _ )| seen.insert( path.to_ascii_lowercase() ) );
+
+    fs::create_dir_all( out_dir )?;
+    // The archive is streamed to a temporary file next to it, so a failed run never leaves half an archive behind
+    let archive = out_dir.join( format!( "{map}.{EXT_ZIP}" ) );
+    let tmp = temp_path( &archive );
+
+    let manifest = write_zip( &tmp, map, entries, missing ).and_then( |manifest| fs::rename( &tmp, &archive ).map( |()| manifest ) );
+
+    if manifest.is_err()
+    {
+        let _ = fs::remove_file( &tmp );
+    }
+
+    manifest
+}
+/// Packages each BSP in the folder that passes the whitelist. Stock content is whatever is in the `svencoop` folder from the config.
+/// Maps with missing resources are still packaged, their manifests list what is missing.
+pub fn package_maps(maps_dir: &Path, whitelist: &[String], out_dir: &Path) -> io::Result<(Report, Vec<Manifest>)>
+{
+    let bsps = filter_bsps( load_bsps( maps_dir ), whitelist );
+
+    if bsps.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::NotFound, "No matching BSP files found." ) );
+    }
+
+    let ( bsps, skipped ) = check_maps( bsps );
+    let stock_dir = Config::get().svencoopdir.clone();
+    let mut report = Report::default();
+    let mut manifests = vec![];
+
+    for bsp in bsps
+    {
+        let archive = out_dir.join( bsp.with_extension( EXT_ZIP ).file_name().unwrap_or_default() );
+
+        match package_map( &bsp, out_dir, stock_dir.as_deref() )
+        {
+            Ok( manifest ) =>
+            {
+                report.push( FileReport::new( archive, Outcome::Written ) );
+                manifests.push( manifest );
+            }
+
+            Err( e ) => report.push( FileReport::new( archive, Outcome::Failed( e ) ) )
+        }
+    }
+
+    report.extend( skipped );
+    Ok( ( report, manifests ) )
+}
+/// Names of the files in the maps folder that belong to the map, as spelled on disk.
+fn companions(maps_dir: &Path, map: &str) -> io::Result<Vec<String>>
+{
+    let wanted: Vec<_> = COMPANION_SUFFIXES.iter().map( |suffix| format!( "{map}{suffix}" ).to_ascii_lowercase() ).collect();
+
+    let mut names: Vec<_> = fs::read_dir( maps_dir )?
+        .filter_map( Result::ok )
+        .filter( |e| e.file_type().is_ok_and( |t| t.is_file() ) )
+        .map( |e| e.file_name().to_string_lossy().into_owned() )
+        .filter( |name| wanted.contains( &name.to_ascii_lowercase() ) )
+    .collect();
+
+    names.sort();
+    Ok( names )
+}
+/// Writes the entries, pairs of a path in the archive and the file to read, to a Deflate compressed ZIP archive at `archive`
+/// one file at a time, then adds the manifest of what went into it as `<map>_manifest.json` and returns it.
+fn write_zip(archive: &Path, map: String, entries: Vec<(String, PathBuf)>, missing: Vec<String>) -> io::Result<Manifest>
+{
+    let mut zip = ZipWriter::new( BufWriter::new( File::create( archive )? ) );
+    let options = SimpleFileOptions::default().compression_method( CompressionMethod::Deflated );
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut files = vec![];
+
+    for ( path, source ) in entries
+    {
+        let mut file = File::open( &source )?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified().map( zip_time ).unwrap_or_default();
+
+        zip.start_file( path.as_str(), options.last_modified_time( modified ).large_file( metadata.len() >= u32::MAX as u64 ) )?;
+
+        let mut crc = crc32fast::Hasher::new();
+        let mut size = 0;
+
+        loop
+        {
+            let read = file.read( &mut buffer )?;
+
+            if read == 0
+            {
+                break;
+            }
+
+            crc.update( &buffer[..read] );
+            zip.write_all( &buffer[..read] )?;
+            size += read as u64;
+        }
+
+        files.push( PackedFile { path, source, size, crc32: format!( "{:08x}", crc.finalize() ) } );
+    }
+
+    let created = SystemTime::now().duration_since( UNIX_EPOCH ).unwrap_or_default().as_secs();
+    let manifest = Manifest { map, created, files, missing };
+
+    zip.start_file( format!( "{}_manifest.json", manifest.map ), options.last_modified_time( zip_time( SystemTime::now() ) ) )?;
+    zip.write_all( serde_json::to_string_pretty( &manifest ).map_err( io::Error::other )?.as_bytes() )?;
+    zip.finish()?.into_inner().map_err( io::IntoInnerError::into_error )?.sync_all()?;
+
+    Ok( manifest )
+}
+/// A timestamp as stored in a ZIP archive, in UTC. Times the archive can't hold become 1980-01-01.
+fn zip_time(time: SystemTime) -> DateTime
+{
+    let time = OffsetDateTime::from( time );
+    DateTime::try_from( PrimitiveDateTime::new( time.date(), time.time() ) ).unwrap_or_default()
+}
@@ -17,6 +17,12 @@ along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
 pub use crate::
 {
+    cfgdoc::
+    {
+        CfgDocument,
+        Cvar,
+        Entry
+    },
     config::Config,
     cvar::
     {
@@ -24,12 +30,17 @@ pub use crate::
         WriteType,
         EXT_BSP,
         DEFAULT_MAP_SETTINGS,
+        create_motd,
         get_default_cvars,
         get_skill_cvars,
         load_bsps
     },
+    diff::FileChange,
     replacements::
     {
+        Conflict,
+        GameRoots,
+        MatchRules,
         Replacement,
         EXT_GMR,
         EXT_GSR,
@@ -42,5 +53,11 @@ pub use crate::
         MaterialKind,
         read_texture_names
     },
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
     utils::HasExtension
 };
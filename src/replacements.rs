@@ -17,20 +17,45 @@ along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
 use std::
 {
+    collections::HashMap,
     fmt::
     {
         self,
         Display
     },
+    fs,
     io,
-    path::Path
+    path::
+    {
+        Path,
+        PathBuf
+    }
 };
 
-use crate::utils::
+use crate::
 {
-    HasExtension,
-    read_trimmed_lines,
-    write_lines
+    config::Config,
+    lint::
+    {
+        Diagnostic,
+        Severity
+    },
+    report::
+    {
+        FileReport,
+        Outcome
+    },
+    resources::
+    {
+        CONTENT_DIRS,
+        content_dirs
+    },
+    spr::EXT_SPR,
+    utils::
+    {
+        HasExtension,
+        write_lines
+    }
 };
 
 pub const EXT_GSR: &str = "gsr";
@@ -42,6 +67,196 @@ pub static EXTS_SOUNDS: [&str; 16] =
     "aiff", "asf", "dls", "flac", "it", "m3u", "mid", "mod", "mp2", "mp3", "ogg", "s3m", "vag", "wav", "wma", "xm"
 ];
 
+/// How `Replacement::match_dirs` pairs up the files of two folders.
+#[derive( Clone, Debug )]
+pub struct MatchRules
+{
+    /// Endings dropped from file names before comparing, so `barney_hd.mdl` pairs with `barney.mdl`.
+    pub ignore_suffixes: Vec<String>,
+    /// Pairs files by name alone when their folders differ, as long as the name is unique on both sides.
+    pub by_name: bool
+}
+
+impl Default for MatchRules
+{
+    fn default() -> Self
+    {
+        Self { ignore_suffixes: vec!["_hd".into()], by_name: true }
+    }
+}
+
+impl MatchRules
+{   /// Reads a comma or space separated list of suffixes.
+    pub fn parse_suffixes(text: &str) -> Vec<String>
+    {
+        text
+            .split( |c: char| c == ',' || c.is_whitespace() )
+            .filter( |s| !s.is_empty() )
+            .map( str::to_owned )
+        .collect()
+    }
+    /// Key files are compared by: the kind of file, the folder, and the name without extension or ignored suffixes.
+    /// Sounds of any format pair with each other, models only with models and sprites only with sprites.
+    fn key(&self, relative: &Path) -> Option<(String, String)>
+    {
+        let name = relative.to_str()?;
+        let kind = if name.has_extension( &EXTS_MODELS ) { relative.extension()?.to_str()?.to_ascii_lowercase() } else { "sound".into() };
+
+        let mut stem = relative.file_stem()?.to_str()?.to_ascii_lowercase();
+
+        while let Some( suffix ) = self.ignore_suffixes.iter().find( |s| !s.is_empty() && stem.ends_with( &s.to_ascii_lowercase() ) )
+        {
+            stem.truncate( stem.len() - suffix.len() );
+        }
+
+        let dir = relative.parent().unwrap_or( Path::new( "" ) ).to_string_lossy().replace( '\\', "/" ).to_ascii_lowercase();
+
+        Some( ( format!( "{kind}:{dir}/{stem}" ), format!( "{kind}:{stem}" ) ) )
+    }
+}
+/// Files paired up between two folders, and the ones that found no partner.
+#[derive( Clone, Default )]
+pub struct DirMatch
+{
+    pub replacements: Vec<Replacement>,
+    /// The files each replacement was made from, in the same order.
+    pub files: Vec<(PathBuf, PathBuf)>,
+    /// Files in the "from" folder, relative to it.
+    pub unmatched: Vec<String>,
+    /// Files in the "from" folder, relative to it, whose partner has the same game path, so replacing them would change nothing.
+    pub same: Vec<String>,
+    /// Why pairs that were found can't be used, usually because a file is outside the game's content folders.
+    pub rejected: Vec<String>
+}
+/// Content folders that the paths in .gmr/.gsr files are relative to. The game can't load files outside them.
+#[derive( Clone, Debug, Default )]
+pub struct GameRoots
+{
+    pub roots: Vec<PathBuf>
+}
+
+impl GameRoots
+{   /// The content folders of the install the `svencoop` folder from the config belongs to.
+    pub fn from_config() -> Self
+    {
+        let roots = Config::get().svencoopdir
+            .as_deref()
+            .and_then( Path::parent )
+            .map( content_dirs )
+        .unwrap_or_default();
+
+        Self { roots }
+    }
+    /// Turns a path into the one the game uses, with forward slashes: relative to the game folder for models and sprites (`models/x.mdl`),
+    /// relative to the `sound` folder for sounds. Absolute paths must be inside one of the roots, or inside a folder named like a content folder.
+    /// Relative paths are taken as already relative to the game, or for sounds to either the game or the `sound` folder.
+    pub fn resolve(&self, path: &str) -> io::Result<String>
+    {
+        let invalid = |reason: &str| io::Error::new( io::ErrorKind::InvalidInput, format!( "'{path}' {reason}." ) );
+
+        let parts = split_path( path );
+
+        if parts.contains( &".." )
+        {
+            return Err( invalid( "goes up a folder" ) );
+        }
+
+        let absolute = Path::new( path ).is_absolute()
+            || path.trim_start().starts_with( ['/', '\\'] )
+            || path.trim_start().chars().nth( 1 ) == Some( ':' );
+
+        let relative =
+        if absolute
+        {
+            let under_root = self.roots
+                .iter()
+                .filter_map( |root|
+                {
+                    let root = root.to_string_lossy();
+                    let root = split_path( &root );
+
+                    ( root.len() < parts.len() && root.iter().zip( &parts ).all( |( a, b )| a.eq_ignore_ascii_case( b ) ) ).then_some( root.len() )
+                })
+            .max();
+            // Failing that, a content folder of another install or a copy of one
+            let under_content_dir = || parts
+                .iter()
+                .rposition( |part| CONTENT_DIRS.iter().any( |d| d.eq_ignore_ascii_case( part ) ) )
+            .map( |i| i + 1 );
+
+            let start = under_root.or_else( under_content_dir ).ok_or_else( || invalid( "is outside the game's content folders" ) )?;
+            &parts[start..]
+        }
+        else
+        {
+            &parts[..]
+        };
+
+        let in_folder = |folders: &[&str]| relative.len() > 1 && folders.iter().any( |f| f.eq_ignore_ascii_case( relative[0] ) );
+
+        match Replacement::classify( path ).ok_or_else( || invalid( "is not a model, sprite or sound" ) )?
+        {
+            Replacement::Models { .. } if in_folder( &["models", "sprites"] ) => Ok( relative.join( "/" ) ),
+            Replacement::Models { .. } => Err( invalid( "is not inside the models or sprites folder" ) ),
+            Replacement::Sounds { .. } if in_folder( &["sound"] ) => Ok( relative[1..].join( "/" ) ),
+            Replacement::Sounds { .. } if absolute => Err( invalid( "is not inside the sound folder" ) ),
+            Replacement::Sounds { .. } => Ok( relative.join( "/" ) )
+        }
+    }
+    /// Finds the file a path from a .gmr/.gsr file refers to, in the highest priority root that has it.
+    pub fn locate(&self, game_path: &str) -> Option<PathBuf>
+    {
+        let path =
+        match Replacement::classify( game_path )?
+        {
+            Replacement::Models { .. } => game_path.to_string(),
+            Replacement::Sounds { .. } => format!( "sound/{game_path}" )
+        };
+
+        self.roots.iter().map( |root| root.join( &path ) ).find( |p| p.is_file() )
+    }
+}
+/// Parts of a path split on either separator, without empty and `.` parts.
+fn split_path(path: &str) -> Vec<&str>
+{
+    path
+        .trim()
+        .split( ['/', '\\'] )
+        .filter( |part| !part.is_empty() && *part != "." )
+    .collect()
+}
+/// What was read from a .gmr/.gsr file.
+#[derive( Clone, Default )]
+pub struct ReplacementFile
+{
+    pub replacements: Vec<Replacement>,
+    /// Line each replacement was read from, starting at 1.
+    pub lines: Vec<usize>,
+    /// Lines that were skipped or look wrong.
+    pub diagnostics: Vec<Diagnostic>
+}
+/// An original that the merged replacements give different new files.
+#[derive( Clone, Debug )]
+pub struct Conflict
+{
+    pub original: String,
+    /// Each new file the original was given and where it came from. The first one is kept.
+    pub choices: Vec<(String, String)>
+}
+
+impl Display for Conflict
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let choices: Vec<_> = self.choices
+            .iter()
+            .map( |( new, source )| if source.is_empty() { format!( "\"{new}\"" ) } else { format!( "\"{new}\" ({source})" ) } )
+        .collect();
+
+        write!( f, "\"{}\" -> {}", self.original, choices.join( ", " ) )
+    }
+}
+
 #[derive( Clone )]
 pub enum Replacement
 {
@@ -66,15 +281,26 @@ impl Replacement
             None
         }
     }
-    /// Attempts to create a new Replacement instance from two paths.
-    pub fn try_new(from: &str, to: &str) -> Option<Self>
+    /// Creates a replacement from two paths, see `GameRoots::resolve`. Both must be models, both sprites, or both sounds.
+    pub fn try_new_in(roots: &GameRoots, from: &str, to: &str) -> io::Result<Self>
     {
-        let ( from, to ) = ( Self::truncate_path( from ), Self::truncate_path( to ) );
+        let unknown = |path: &str| io::Error::new( io::ErrorKind::InvalidInput, format!( "'{path}' is not a model, sprite or sound." ) );
+        let kind = Self::classify( from ).ok_or_else( || unknown( from ) )?;
 
-        match Self::classify( &from )?
+        match ( &kind, Self::classify( to ).ok_or_else( || unknown( to ) )? )
         {
-            Self::Models { .. } => Some( Self::Models { original: from, new: to } ),
-            Self::Sounds { .. } => Some( Self::Sounds { original: from, new: to } )
+            ( Self::Models { .. }, Self::Sounds { .. } ) |
+            ( Self::Sounds { .. }, Self::Models { .. } ) =>
+            {
+                Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "'{from}' and '{to}' are not the same kind of file." ) ) )
+            }
+            // Models and sprites share a list, but an entity drawn with one breaks when given the other
+            ( Self::Models { .. }, _ ) if from.has_extension( &[EXT_SPR] ) != to.has_extension( &[EXT_SPR] ) =>
+            {
+                Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "'{from}' and '{to}' are not both models or both sprites." ) ) )
+            }
+
+            _ => Ok( kind.with( &roots.resolve( from )?, &roots.resolve( to )? ) )
         }
     }
     /// Gets the original item.
@@ -95,117 +321,318 @@ impl Replacement
             Self::Sounds { new, .. } => new,
         }
     }
-    /// Gets replacement mappings from a file.
-    pub fn from_file(path: &Path) -> Option<Vec<Self>>
+    /// Reads a .gmr/.gsr file, see `Replacement::parse`.
+    pub fn from_file(path: &Path, roots: &GameRoots) -> io::Result<ReplacementFile>
     {
-        let mut replacements = vec![];
+        Ok( Self::parse( &String::from_utf8_lossy( &fs::read( path )? ), roots ) )
+    }
+    /// Parses the text of a .gmr/.gsr file. Each line holds the original and new path, quoted or not, separated by any whitespace.
+    /// `//` starts a comment outside quotes. Lines that can't be used are skipped with an error, questionable ones are kept with a warning.
+    /// Paths are resolved against `roots`, see `GameRoots::resolve`.
+    pub fn parse(text: &str, roots: &GameRoots) -> ReplacementFile
+    {
+        let mut parsed = ReplacementFile::default();
+        let mut originals: HashMap<String, usize> = HashMap::new();
 
-        for mapping in read_trimmed_lines( path )?
+        for ( idx, raw ) in text.lines().enumerate()
         {
-            let parts: Vec<_> = mapping.split( '"' ).collect();
-            let mut quoted = parts
-                .iter()
-                .enumerate()
-                .filter( |( i, _ )| i % 2 == 1 )
-                .map( |( _, s )| s.trim() )
-            .filter( |s| !s.is_empty() );
+            let line = idx + 1;
+            let mut report = |column, severity, message: String| parsed.diagnostics.push( Diagnostic { line, column, severity, message } );
 
-            let Some( from ) = quoted.next()
-            else
+            let tokens =
+            match tokenize( raw )
             {
-                continue
+                Ok( tokens ) => tokens,
+                Err( column ) =>
+                {
+                    report( column, Severity::Error, "Quote is never closed, line skipped.".into() );
+                    continue;
+                }
             };
 
-            let Some( to ) = quoted.next()
-            else
+            let ( ( from_col, from ), ( to_col, to ) ) =
+            match tokens.as_slice()
+            {
+                [] => continue,
+                [( column, token )] =>
+                {
+                    report( *column, Severity::Error, format!( "'{token}' has no replacement, line skipped." ) );
+                    continue;
+                }
+
+                [from, to, rest @ ..] =>
+                {
+                    if let Some( ( column, _ ) ) = rest.first()
+                    {
+                        report( *column, Severity::Warning, "Extra text after the replacement is ignored.".into() );
+                    }
+
+                    ( from.clone(), to.clone() )
+                }
+            };
+
+            if let Some( ( column, _ ) ) = [( from_col, &from ), ( to_col, &to )].into_iter().find( |( _, t )| t.is_empty() )
+            {
+                report( column, Severity::Error, "Empty path, line skipped.".into() );
+                continue;
+            }
+
+            let replacement =
+            match Self::try_new_in( roots, &from, &to )
             {
-                continue
+                Ok( r ) => r,
+                Err( e ) =>
+                {   // Point at the path that is wrong
+                    let column = if Self::classify( &from ).is_some() && roots.resolve( &from ).is_ok() { to_col } else { from_col };
+                    report( column, Severity::Error, format!( "{e} Line skipped." ) );
+                    continue;
+                }
             };
 
-            if let Some( replacement ) = Self::try_new( from, to )
-            && !replacement.is_redundant()
+            if replacement.is_redundant()
+            {
+                report( from_col, Severity::Warning, format!( "'{from}' is replaced with itself, line skipped." ) );
+                continue;
+            }
+
+            match originals.get( &replacement.get_original().to_ascii_lowercase() )
             {
-                replacements.push( replacement );
+                Some( first ) => report( from_col, Severity::Warning, format!( "'{from}' is already replaced on line {first}." ) ),
+                None => { originals.insert( replacement.get_original().to_ascii_lowercase(), line ); }
             }
+
+            parsed.replacements.push( replacement );
+            parsed.lines.push( line );
         }
 
-        Some( replacements )
+        parsed
+    }
+    /// Splits the collected replacements into models and sounds
+    pub fn partition_replacements(replacements: &[Self]) -> (Vec<Self>, Vec<Self>)
+    {
+        replacements
+            .iter()
+            .cloned()
+        .partition( |r| matches!( r, Replacement::Models { .. } ) )
+    }
+    /// Writes the replacements to a file in txt format, the extension is picked from the replacement type.
+    pub fn to_file(filename: &str, replacements: &[Self]) -> FileReport
+    {
+        let ext = 
+        match replacements.first()
+        {
+            Some( Self::Models { .. } ) => EXT_GMR,
+            Some( Self::Sounds { .. } ) => EXT_GSR,
+            None => return FileReport::new( filename, Outcome::Failed( io::Error::new( io::ErrorKind::InvalidFilename, "Invalid extension." ) ) )
+        };
+
+        let result = write_lines( filename, ext, replacements ).map( |()| Outcome::Written );
+        FileReport::from_result( format!( "{filename}.{ext}" ), result )
     }
-    /// Chops off the absolute path of a model/sprite/sound file to only include the relative path from the models/sprites/sound directory.
-    pub fn truncate_path(path: &str) -> String
+    /// Pairs each model or sound under `from_root` with the one under `to_root` at the same relative path,
+    /// or failing that with the same name if `rules.by_name` is set. Files are compared by the rules' keys, see `MatchRules`.
+    /// The paths of each pair are resolved against `roots`.
+    pub fn match_dirs(from_root: &Path, to_root: &Path, rules: &MatchRules, roots: &GameRoots) -> DirMatch
     {
-        if let Some( split ) = path.split_once( "models" )
+        let keyed = |root: &Path| -> Vec<(PathBuf, String, String)>
         {
-            format!( "models{}", split.1 )
-        }
-        else if let Some( split ) = path.split_once( "sprites" )
+            let mut files: Vec<_> = jwalk::WalkDir::new( root )
+                .into_iter()
+                .filter_map( Result::ok )
+                .filter( |e| e.file_type().is_file() )
+                .map( |e| e.path() )
+                .filter( |p| Self::classify( &p.to_string_lossy() ).is_some() )
+                .filter_map( |p|
+                {
+                    let ( path_key, name_key ) = rules.key( p.strip_prefix( root ).ok()? )?;
+                    Some( ( p, path_key, name_key ) )
+                })
+            .collect();
+
+            files.sort_by( |a, b| a.0.cmp( &b.0 ) );
+            files
+        };
+
+        let ( from_files, to_files ) = ( keyed( from_root ), keyed( to_root ) );
+
+        let mut by_path = HashMap::new();
+        let mut by_name: HashMap<&str, Vec<&Path>> = HashMap::new();
+
+        for ( path, path_key, name_key ) in &to_files
         {
-            format!( "sprites{}", split.1 )
+            by_path.entry( path_key.as_str() ).or_insert( path.as_path() );
+            by_name.entry( name_key.as_str() ).or_default().push( path );
         }
-        else if let Some( split ) = path.split_once( "sound" )
+
+        let mut from_names: HashMap<&str, usize> = HashMap::new();
+
+        for ( _, _, name_key ) in &from_files
         {
-            split.1.to_string()
+            *from_names.entry( name_key.as_str() ).or_default() += 1;
         }
-        else// File is not inside expected directories, return the filename and parent directory name.
+
+        let mut result = DirMatch::default();
+
+        for ( from, path_key, name_key ) in &from_files
         {
-            let file_path = Path::new( path );
+            let to = by_path.get( path_key.as_str() ).copied().or_else( ||
+            {
+                match by_name.get( name_key.as_str() ).map( Vec::as_slice )
+                {
+                    Some( [only] ) if rules.by_name && from_names[name_key.as_str()] == 1 => Some( *only ),
+                    _ => None
+                }
+            });
 
-            let filename = file_path
-                .file_name()
-                .and_then( |s| s.to_str() )
-            .unwrap_or( path );
+            let Some( to ) = to
+            else
+            {
+                result.unmatched.push( from.strip_prefix( from_root ).unwrap_or( from ).to_string_lossy().into_owned() );
+                continue;
+            };
+
+            match Self::try_new_in( roots, &from.to_string_lossy(), &to.to_string_lossy() )
+            {
+                Ok( replacement ) if replacement.is_redundant() =>
+                {
+                    result.same.push( from.strip_prefix( from_root ).unwrap_or( from ).to_string_lossy().into_owned() );
+                }
 
-            let parent = file_path
-                .parent()
-                .and_then( |p| p.file_name() )
-                .and_then( |s| s.to_str() )
-            .unwrap_or( "" );
+                Ok( replacement ) =>
+                {
+                    result.replacements.push( replacement );
+                    result.files.push( ( from.clone(), to.to_path_buf() ) );
+                }
 
-            let ext = file_path.extension()
-                .and_then( |s| s.to_str() )
-            .unwrap_or( "" );
+                Err( e ) => result.rejected.push( e.to_string() )
+            }
+        }
+
+        result
+    }
+    /// Merges sets of replacements, each named by where it came from. Originals are compared ignoring case.
+    /// The first set to replace an original wins, later ones giving it a different new file are returned as conflicts.
+    pub fn merge<'a>(sets: impl IntoIterator<Item = (&'a str, &'a [Self])>) -> (Vec<Self>, Vec<Conflict>)
+    {
+        let mut merged: Vec<Self> = vec![];
+        let mut sources = vec![];
+        let mut conflicts: Vec<Conflict> = vec![];
+        let mut index = HashMap::new();
 
-            let combined = format!( "{parent}/{filename}" );
-            match ext
+        for ( source, replacements ) in sets
+        {
+            for r in replacements
             {
-                "mdl" => format!( "models/{combined}" ),
-                "spr" => format!( "sprites/{combined}" ),
-                _ if EXTS_SOUNDS.contains( &ext ) =>
+                let key = r.get_original().to_ascii_lowercase();
+
+                let Some( &i ) = index.get( &key )
+                else
                 {
-                    if parent.is_empty()
-                    { 
-                        filename.to_string()
-                    }
-                    else
-                    {
-                        combined
-                    }
+                    index.insert( key, merged.len() );
+                    merged.push( r.clone() );
+                    sources.push( source );
+                    continue;
+                };
+
+                if merged[i].get_new().eq_ignore_ascii_case( r.get_new() )
+                {
+                    continue;
                 }
 
-                _ => filename.to_string()
+                let choice = ( r.get_new().to_string(), source.to_string() );
+
+                match conflicts.iter_mut().find( |c| c.original.eq_ignore_ascii_case( r.get_original() ) )
+                {
+                    Some( conflict ) => conflict.choices.push( choice ),
+                    None => conflicts.push( Conflict
+                    {
+                        original: merged[i].get_original().to_string(),
+                        choices: vec![( merged[i].get_new().to_string(), sources[i].to_string() ), choice]
+                    })
+                }
             }
         }
+
+        ( merged, conflicts )
     }
-    /// Splits the collected replacements into models and sounds
-    pub fn partition_replacements(replacements: &[Self]) -> (Vec<Self>, Vec<Self>)
+    /// Swaps the original and new file, to undo a replacement.
+    pub fn inverted(&self) -> Self
     {
-        replacements
-            .iter()
-            .cloned()
-        .partition( |r| matches!( r, Replacement::Models { .. } ) )
+        self.with( self.get_new(), self.get_original() )
     }
-    /// Writes the replacements to a file in txt format
-    pub fn to_file(filename: &str, replacements: &[Self]) -> io::Result<()>
+    /// Inverts each replacement. A new file used by several originals can only go back to one of them,
+    /// the first is kept and the rest are returned as conflicts.
+    pub fn invert(replacements: &[Self]) -> (Vec<Self>, Vec<Conflict>)
     {
-        let ext = 
-        match replacements.first()
+        let inverted: Vec<_> = replacements.iter().map( Self::inverted ).collect();
+        Self::merge( [( "", inverted.as_slice() )] )
+    }
+    /// Collapses chains so each original points straight at the file at the end of its chain: `a -> b` and `b -> c` become `a -> c` and `b -> c`.
+    /// Replacements that lead into a cycle are left as they are, and each cycle is returned once.
+    pub fn collapse(replacements: &[Self]) -> (Vec<Self>, Vec<Vec<String>>)
+    {
+        let ( replacements, _ ) = Self::merge( [( "", replacements )] );
+
+        let next: HashMap<_, _> = replacements
+            .iter()
+            .map( |r| ( r.get_original().to_ascii_lowercase(), r.get_new() ) )
+        .collect();
+
+        let mut collapsed = vec![];
+        let mut cycles: Vec<Vec<String>> = vec![];
+
+        for r in &replacements
         {
-            Some( Self::Models { .. } ) => EXT_GMR,
-            Some( Self::Sounds { .. } ) => EXT_GSR,
-            None => return Err( io::Error::new( io::ErrorKind::InvalidFilename, "Invalid extension." ) )
-        };
+            let mut chain = vec![r.get_original()];
+            let mut current = r.get_new();
+
+            loop
+            {
+                if let Some( start ) = chain.iter().position( |c| c.eq_ignore_ascii_case( current ) )
+                {
+                    let mut cycle: Vec<_> = chain[start..].iter().map( |c| c.to_string() ).collect();
+                    // Start each cycle at the same member so the same cycle found from another entry is recognised
+                    let first = ( 0..cycle.len() ).min_by_key( |&i| cycle[i].to_ascii_lowercase() ).unwrap_or( 0 );
+                    cycle.rotate_left( first );
+
+                    if !cycles.iter().any( |c| c.len() == cycle.len() && c.iter().zip( &cycle ).all( |( a, b )| a.eq_ignore_ascii_case( b ) ) )
+                    {
+                        cycles.push( cycle );
+                    }
+
+                    collapsed.push( r.clone() );
+                    break;
+                }
+
+                match next.get( &current.to_ascii_lowercase() )
+                {
+                    Some( new ) =>
+                    {
+                        chain.push( current );
+                        current = new;
+                    }
+
+                    None =>
+                    {
+                        collapsed.push( r.with( r.get_original(), current ) );
+                        break;
+                    }
+                }
+            }
+        }
+
+        ( collapsed, cycles )
+    }
+    /// A replacement of the same kind with different files.
+    fn with(&self, original: &str, new: &str) -> Self
+    {
+        let ( original, new ) = ( original.to_string(), new.to_string() );
 
-        write_lines( filename, ext, replacements )
+        match self
+        {
+            Self::Models { .. } => Self::Models { original, new },
+            Self::Sounds { .. } => Self::Sounds { original, new }
+        }
     }
     /// Checks whether both the original and new is identical.
     /// Returns true if they match.
@@ -222,3 +649,43 @@ impl Display for Replacement
         write!( f, "\"{}\" \"{}\"", self.get_original(), self.get_new() )
     }
 }
+/// Splits a line into its tokens and the column each starts at. Quoted tokens may hold spaces, `//` outside quotes ends the line.
+/// Fails with the column of a quote that is never closed.
+pub(crate) fn tokenize(line: &str) -> Result<Vec<(usize, String)>, usize>
+{
+    let chars: Vec<_> = line.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len()
+    {
+        match chars[i]
+        {
+            c if c.is_whitespace() => i += 1,
+            '/' if chars.get( i + 1 ) == Some( &'/' ) => break,
+            '"' =>
+            {
+                let close = chars[i + 1..].iter().position( |&c| c == '"' ).ok_or( i + 1 )?;
+                tokens.push( ( i + 1, chars[i + 1..i + 1 + close].iter().collect::<String>().trim().to_owned() ) );
+                i += close + 2;
+            }
+
+            _ =>
+            {
+                let start = i;
+
+                while i < chars.len()
+                && !chars[i].is_whitespace()
+                && chars[i] != '"'
+                && !( chars[i] == '/' && chars.get( i + 1 ) == Some( &'/' ) )
+                {
+                    i += 1;
+                }
+
+                tokens.push( ( start + 1, chars[start..i].iter().collect() ) );
+            }
+        }
+    }
+
+    Ok( tokens )
+}
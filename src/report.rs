@@ -0,0 +1,157 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fmt::
+    {
+        self,
+        Display
+    },
+    io,
+    path::PathBuf
+};
+/// What happened to a single file during an operation.
+#[derive( Debug )]
+pub enum Outcome
+{
+    Written,
+    Deleted,
+    Skipped( String ),
+    Failed( io::Error )
+}
+
+impl Outcome
+{
+    pub fn is_success(&self) -> bool
+    {
+        matches!( self, Self::Written | Self::Deleted )
+    }
+}
+
+impl Display for Outcome
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::Written => write!( f, "written" ),
+            Self::Deleted => write!( f, "deleted" ),
+            Self::Skipped( reason ) => write!( f, "skipped: {reason}" ),
+            Self::Failed( e ) => write!( f, "failed: {e}" )
+        }
+    }
+}
+/// Outcome of an operation on one file.
+#[derive( Debug )]
+pub struct FileReport
+{
+    pub path: PathBuf,
+    pub outcome: Outcome
+}
+
+impl FileReport
+{
+    pub fn new(path: impl Into<PathBuf>, outcome: Outcome) -> Self
+    {
+        Self { path: path.into(), outcome }
+    }
+    /// Turns the result of a write into a report, a failed write becomes Outcome::Failed.
+    pub fn from_result(path: impl Into<PathBuf>, result: io::Result<Outcome>) -> Self
+    {
+        Self::new( path, result.unwrap_or_else( Outcome::Failed ) )
+    }
+}
+
+impl Display for FileReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!( f, "{}: {}", self.path.display(), self.outcome )
+    }
+}
+/// Per-file outcomes of an operation that touches many files.
+/// Leaves it to the caller (GUI or CLI) to decide how to present them.
+#[derive( Debug, Default )]
+pub struct Report
+{
+    pub files: Vec<FileReport>
+}
+
+impl Report
+{
+    pub fn push(&mut self, file: FileReport)
+    {
+        self.files.push( file );
+    }
+    /// Number of files that were written or deleted.
+    pub fn succeeded(&self) -> usize
+    {
+        self.files.iter().filter( |f| f.outcome.is_success() ).count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &FileReport>
+    {
+        self.files.iter().filter( |f| matches!( f.outcome, Outcome::Failed( _ ) ) )
+    }
+
+    pub fn has_failures(&self) -> bool
+    {
+        self.failures().next().is_some()
+    }
+    /// Short human readable summary, listing any failures.
+    pub fn summary(&self, kind: &str) -> String
+    {
+        let mut summary = format!( "Processed {} {kind} file(s).", self.succeeded() );
+
+        if self.has_failures()
+        {
+            summary.push_str( "\n\nFailed:" );
+
+            for f in self.failures()
+            {
+                summary.push_str( &format!( "\n{f}" ) );
+            }
+        }
+
+        summary
+    }
+}
+
+impl From<FileReport> for Report
+{
+    fn from(file: FileReport) -> Self
+    {
+        Self { files: vec![file] }
+    }
+}
+
+impl Extend<FileReport> for Report
+{
+    fn extend<I: IntoIterator<Item = FileReport>>(&mut self, iter: I)
+    {
+        self.files.extend( iter );
+    }
+}
+
+impl FromIterator<FileReport> for Report
+{
+    fn from_iter<I: IntoIterator<Item = FileReport>>(iter: I) -> Self
+    {
+        Self { files: iter.into_iter().collect() }
+    }
+}
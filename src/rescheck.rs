@@ -0,0 +1,205 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    io,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+use serde::Serialize;
+
+use crate::
+{
+    config::Config,
+    cvar::
+    {
+        filter_bsps,
+        load_bsps
+    },
+    resources::
+    {
+        Resource,
+        ResourceKind,
+        content_dirs,
+        map_resources
+    }
+};
+/// Where the game finds a file a map needs.
+#[derive( Clone, Debug, Serialize )]
+pub struct ResourceStatus
+{
+    pub path: String,
+    pub kind: ResourceKind,
+    pub source: String,
+    /// Content folder the game loads the file from, `None` if no folder has it.
+    pub found_in: Option<String>,
+    /// Lower priority folders that also have the file. Their copies are never loaded.
+    pub shadowed: Vec<String>
+}
+
+impl ResourceStatus
+{
+    fn resolve(resource: Resource, dirs: &[PathBuf]) -> Self
+    {
+        let mut found = dirs
+            .iter()
+            .filter( |d| d.join( &resource.path ).is_file() )
+        .map( |d| d.file_name().unwrap_or_default().to_string_lossy().into_owned() );
+
+        let found_in = found.next();
+        let shadowed = found.collect();
+
+        Self { path: resource.path, kind: resource.kind, source: resource.source, found_in, shadowed }
+    }
+}
+/// Resources of one map and where each one was found.
+#[derive( Clone, Debug, Serialize )]
+pub struct MapCheck
+{
+    pub bsp: String,
+    pub resources: Vec<ResourceStatus>,
+    /// Why the BSP could not be read, if it couldn't.
+    #[serde( skip_serializing_if = "Option::is_none" )]
+    pub error: Option<String>
+}
+
+impl MapCheck
+{
+    pub fn missing(&self) -> impl Iterator<Item = &ResourceStatus>
+    {
+        self.resources.iter().filter( |r| r.found_in.is_none() )
+    }
+
+    pub fn shadowed(&self) -> impl Iterator<Item = &ResourceStatus>
+    {
+        self.resources.iter().filter( |r| !r.shadowed.is_empty() )
+    }
+}
+/// Checks the resources of the maps in a folder against the content folders of a Sven Co-op install.
+#[derive( Clone, Debug, Serialize )]
+pub struct ResourceCheck
+{
+    pub install_dir: PathBuf,
+    /// Content folders searched, highest priority first.
+    pub searched: Vec<String>,
+    pub maps: Vec<MapCheck>
+}
+
+impl ResourceCheck
+{   /// Checks against the install the `svencoop` folder from the config belongs to.
+    pub fn run(maps_dir: &Path, whitelist: &[String]) -> io::Result<Self>
+    {
+        let install_dir = Config::get().svencoopdir
+            .as_deref()
+            .and_then( Path::parent )
+        .ok_or_else( || io::Error::new( io::ErrorKind::NotFound, "The Sven Co-op folder is not set." ) )?;
+
+        Self::run_in( install_dir, maps_dir, whitelist )
+    }
+
+    pub fn run_in(install_dir: &Path, maps_dir: &Path, whitelist: &[String]) -> io::Result<Self>
+    {
+        let dirs = content_dirs( install_dir );
+
+        if dirs.is_empty()
+        {
+            return Err( io::Error::new( io::ErrorKind::NotFound,
+                format!( "No content folders found in {}.", install_dir.display() ) ) );
+        }
+
+        let mut bsps = filter_bsps( load_bsps( maps_dir ), whitelist );
+
+        if bsps.is_empty()
+        {
+            return Err( io::Error::new( io::ErrorKind::NotFound, "No matching BSP files found." ) );
+        }
+
+        bsps.sort_by_key( |b| b.to_string_lossy().to_ascii_lowercase() );
+
+        let maps = bsps
+            .iter()
+            .map( |bsp_path|
+            {
+                let bsp = bsp_path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+
+                match map_resources( bsp_path, &dirs )
+                {
+                    Ok( ( resources, missing ) ) =>
+                    {
+                        let resources = resources.into_iter().chain( missing ).map( |r| ResourceStatus::resolve( r, &dirs ) ).collect();
+                        MapCheck { bsp, resources, error: None }
+                    }
+
+                    Err( e ) => MapCheck { bsp, resources: vec![], error: Some( e.to_string() ) }
+                }
+            })
+        .collect();
+
+        let searched = dirs
+            .iter()
+            .map( |d| d.file_name().unwrap_or_default().to_string_lossy().into_owned() )
+        .collect();
+
+        Ok( Self { install_dir: install_dir.to_path_buf(), searched, maps } )
+    }
+
+    pub fn missing_count(&self) -> usize
+    {
+        self.maps.iter().map( |m| m.missing().count() ).sum()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String>
+    {
+        serde_json::to_string_pretty( self )
+    }
+
+    pub fn to_markdown(&self) -> String
+    {
+        let mut out = format!( "# Resource check: {}\n\nSearched, highest priority first: {}\n",
+            self.install_dir.display(), self.searched.join( ", " ) );
+
+        for map in &self.maps
+        {
+            out.push_str( &format!( "\n## {}\n\n", map.bsp ) );
+
+            if let Some( error ) = &map.error
+            {
+                out.push_str( &format!( "Could not read the BSP: {error}\n" ) );
+                continue;
+            }
+
+            out.push_str( &format!( "{} file(s), {} missing, {} shadowed\n\n",
+                map.resources.len(), map.missing().count(), map.shadowed().count() ) );
+
+            out.push_str( "| File | Used by | Found in | Shadowed in |\n" );
+            out.push_str( "|------|---------|----------|-------------|\n" );
+
+            for r in &map.resources
+            {
+                out.push_str( &format!( "| {} | {} | {} | {} |\n",
+                    r.path, r.source, r.found_in.as_deref().unwrap_or( "**missing**" ), r.shadowed.join( ", " ) ) );
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,415 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    collections::HashSet,
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs,
+    io,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+use serde::Serialize;
+use strum::Display as StrumDisplay;
+
+use crate::
+{
+    backup::BackupStore,
+    bsp::Bsp,
+    casecheck::DiskNames,
+    cfgdoc::CfgDocument,
+    config::Config,
+    cvar::
+    {
+        check_maps,
+        filter_bsps,
+        load_bsps
+    },
+    replacements::
+    {
+        EXTS_SOUNDS,
+        GameRoots,
+        Replacement
+    },
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
+    utils::write_atomic
+};
+
+pub const EXT_RES: &str = "res";
+/// Content folders of a Sven Co-op install, in the order the game searches them.
+pub const CONTENT_DIRS: [&str; 4] = ["svencoop_addon", "svencoop_hd", "svencoop", "svencoop_downloads"];
+/// Suffixes of the six skybox images in `gfx/env`.
+const SKY_SIDES: [&str; 6] = ["up", "dn", "lf", "rt", "ft", "bk"];
+/// CVars in a map's cfg that name a file the clients need.
+pub(crate) const CFG_REFERENCES: [(&str, ResourceKind); 5] =
+[
+    ( "globalmodellist", ResourceKind::Replacements ),
+    ( "globalsoundlist", ResourceKind::Replacements ),
+    ( "materials_file", ResourceKind::Materials ),
+    ( "sentence_file", ResourceKind::Sentences ),
+    ( "map_script", ResourceKind::Script )
+];
+
+#[derive( Clone, Copy, Debug, PartialEq, Eq, Hash, StrumDisplay, Serialize )]
+#[strum( serialize_all = "lowercase" )]
+#[serde( rename_all = "lowercase" )]
+pub enum ResourceKind
+{
+    Model,
+    Sprite,
+    Sound,
+    Sentences,
+    Wad,
+    Sky,
+    Replacements,
+    Materials,
+    Script
+}
+/// A file a map needs, as a path relative to the game folder.
+#[derive( Clone, Debug, Serialize )]
+pub struct Resource
+{
+    pub path: String,
+    pub kind: ResourceKind,
+    /// What refers to it, e.g. `ambient_generic.message` or `globalsoundlist`.
+    pub source: String
+}
+
+impl Resource
+{
+    fn new(path: String, kind: ResourceKind, source: impl Into<String>) -> Self
+    {
+        Self { path, kind, source: source.into() }
+    }
+    /// Makes a resource from an entity keyvalue or a replacement target, going by its extension.
+    /// Sounds are stored relative to `sound/`, so the folder is added when it is missing.
+    pub(crate) fn from_value(value: &str, source: &str) -> Option<Self>
+    {
+        let value = normalize( value );
+
+        if value.starts_with( ['*', '!'] )
+        {   // Brush models and sentence names, not files
+            return None;
+        }
+
+        let ext = Path::new( &value ).extension()?.to_str()?.to_ascii_lowercase();
+
+        let ( folder, kind ) =
+        match ext.as_str()
+        {
+            "mdl" => ( "models/", ResourceKind::Model ),
+            "spr" => ( "sprites/", ResourceKind::Sprite ),
+            _ if EXTS_SOUNDS.contains( &ext.as_str() ) => ( "sound/", ResourceKind::Sound ),
+            _ => return None
+        };
+
+        let path =
+        if value.to_ascii_lowercase().starts_with( folder )
+        {
+            value
+        }
+        else
+        {
+            format!( "{folder}{value}" )
+        };
+
+        Some( Self::new( path, kind, source ) )
+    }
+}
+/// Collects every file a map refers to: models, sprites and sounds in its entities, the WADs and skybox from worldspawn,
+/// the files named in its cfg, and the sounds and models those files point to in turn.
+/// Files named in the cfg are looked up in the game folder above the maps folder, then in `search_dirs`.
+/// The ones found in none of them are returned separately, with the path as the cfg writes it.
+/// Duplicates are dropped and the list is sorted.
+pub fn map_resources(bsp_path: &Path, search_dirs: &[PathBuf]) -> io::Result<(Vec<Resource>, Vec<Resource>)>
+{
+    let bsp = Bsp::open( bsp_path )?;
+    let map = bsp_path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let maps_dir = bsp_path.parent().unwrap_or( Path::new( "" ) );
+    let game_dir = maps_dir.parent().unwrap_or( maps_dir );
+
+    let mut dirs = vec![game_dir.to_path_buf()];
+    dirs.extend( search_dirs.iter().filter( |d| d.as_path() != game_dir ).cloned() );
+    // Replacement files are read against the same folders their own files are looked up in
+    let roots = GameRoots { roots: dirs.clone() };
+
+    let mut resources = vec![];
+    let mut missing = vec![];
+
+    for entity in &bsp.entities
+    {
+        resources.extend( entity.keyvalues
+            .iter()
+        .filter_map( |( key, value )| Resource::from_value( value, &format!( "{}.{key}", entity.classname() ) ) ) );
+    }
+
+    resources.extend( bsp.wads().into_iter().map( |wad| Resource::new( wad, ResourceKind::Wad, "worldspawn.wad" ) ) );
+
+    if let Some( sky ) = bsp.skyname()
+    {
+        resources.extend( SKY_SIDES.iter().map( |side| Resource::new( format!( "gfx/env/{sky}{side}.tga" ), ResourceKind::Sky, "worldspawn.skyname" ) ) );
+    }
+
+    if let Ok( cfg ) = CfgDocument::from_file( &bsp_path.with_extension( "cfg" ) )
+    {
+        for ( name, kind ) in CFG_REFERENCES
+        {
+            let Some( cvar ) = cfg.cvars().filter( |c| c.is_named( name ) && !c.value.is_empty() ).last()
+            else
+            {
+                continue;
+            };
+
+            let value = normalize( &cvar.value );
+
+            let Some( ( path, file ) ) = locate( &dirs, &cfg_candidates( name, &map, &value ) )
+            else
+            {
+                missing.push( Resource::new( value, kind, name ) );
+                continue;
+            };
+
+            match kind
+            {
+                ResourceKind::Replacements => resources.extend( Replacement::from_file( &file, &roots )
+                    .map( |parsed| parsed.replacements )
+                    .unwrap_or_default()
+                    .iter()
+                .filter_map( |r| Resource::from_value( r.get_new(), name ) ) ),
+                ResourceKind::Sentences => resources.extend( fs::read_to_string( &file )
+                    .map( |text| sentence_sounds( &text ) )
+                    .unwrap_or_default()
+                    .into_iter()
+                .map( |sound| Resource::new( sound, ResourceKind::Sound, name ) ) ),
+                _ => { }
+            }
+
+            resources.push( Resource::new( path, kind, name ) );
+        }
+    }
+
+    let mut seen = HashSet::new();
+    resources.retain( |r| seen.insert( r.path.to_ascii_lowercase() ) );
+    resources.sort_by_key( |r| r.path.to_ascii_lowercase() );
+
+    Ok( ( resources, missing ) )
+}
+/// Paths a cfg CVar's file could be at, relative to the game folder, the most likely first.
+pub(crate) fn cfg_candidates(cvar: &str, map: &str, value: &str) -> Vec<String>
+{
+    match cvar
+    {
+        "globalmodellist" => vec![format!( "models/{map}/{value}" ), format!( "maps/{value}" ), value.to_owned()],
+        "globalsoundlist" | "materials_file" => vec![format!( "sound/{map}/{value}" ), format!( "maps/{value}" ), value.to_owned()],
+        "sentence_file" => vec![value.to_owned(), format!( "sound/{map}/{value}" ), format!( "sound/{value}" )],
+        "map_script" if Path::new( value ).extension().is_none() => vec![format!( "scripts/maps/{value}.as" )],
+        _ => vec![format!( "scripts/maps/{value}" )]
+    }
+}
+/// Gets the first candidate that exists in any of the folders along with where it was found.
+fn locate(dirs: &[PathBuf], candidates: &[String]) -> Option<(String, PathBuf)>
+{
+    candidates
+        .iter()
+    .find_map( |c| dirs.iter().map( |d| d.join( c ) ).find( |p| p.is_file() ).map( |p| ( c.clone(), p ) ) )
+}
+/// Lists the sound files used by a sentences file. Each word is a sound in the folder
+/// set by the last word that had one, `vox` if none did. Pitch and volume options in brackets are ignored.
+fn sentence_sounds(text: &str) -> Vec<String>
+{
+    let mut sounds = vec![];
+
+    for line in text.lines().map( str::trim ).filter( |l| !l.is_empty() && !l.starts_with( "//" ) )
+    {
+        let mut folder = "vox";
+
+        for word in line.split_whitespace().skip( 1 )
+        {
+            let word = word.split( '(' ).next().unwrap_or_default().trim_end_matches( [',', '.'] );
+
+            let word =
+            match word.rsplit_once( '/' )
+            {
+                Some( ( dir, word ) ) =>
+                {
+                    folder = dir;
+                    word
+                }
+
+                None => word
+            };
+
+            if !word.is_empty()
+            {
+                sounds.push( format!( "sound/{folder}/{word}.wav" ) );
+            }
+        }
+    }
+
+    sounds
+}
+/// Gets the content folders that exist in a Sven Co-op install, highest priority first.
+pub fn content_dirs(install_dir: &Path) -> Vec<PathBuf>
+{
+    CONTENT_DIRS
+        .iter()
+        .map( |d| install_dir.join( d ) )
+        .filter( |d| d.is_dir() )
+    .collect()
+}
+/// Uses single forward slashes and drops surrounding whitespace and leading slashes, as paths are written in `.res` files.
+pub(crate) fn normalize(path: &str) -> String
+{
+    path
+        .trim()
+        .split( ['/', '\\'] )
+        .filter( |part| !part.is_empty() )
+        .collect::<Vec<_>>()
+    .join( "/" )
+}
+/// The resource list a map's clients download.
+#[derive( Clone, Debug )]
+pub struct ResFile
+{
+    pub path: PathBuf,
+    pub resources: Vec<Resource>,
+    /// Files named in the map's cfg that could not be found, listed as comments so they are not downloaded.
+    pub missing: Vec<Resource>
+}
+
+impl ResFile
+{   /// Builds the resource list of a BSP, leaving out files that ship with the game in `stock_dir`, whatever their case.
+    /// Files named in the map's cfg are also looked for in the other content folders next to `stock_dir`.
+    pub fn build(bsp_path: &Path, stock_dir: Option<&Path>) -> io::Result<Self>
+    {
+        let search_dirs = stock_dir.and_then( Path::parent ).map( content_dirs ).unwrap_or_default();
+        let ( resources, missing ) = map_resources( bsp_path, &search_dirs )?;
+        let mut names = DiskNames::default();
+
+        let resources = resources
+            .into_iter()
+            .filter( |r| stock_dir.is_none_or( |dir| names.spell( dir, &r.path ).is_none() ) )
+        .collect();
+
+        Ok( Self { path: bsp_path.with_extension( EXT_RES ), resources, missing } )
+    }
+}
+
+impl Display for ResFile
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let map = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        writeln!( f, "// Resources for {map}, generated by CFGBeast\n" )?;
+
+        for resource in &self.resources
+        {
+            writeln!( f, "{}", resource.path )?;
+        }
+
+        for resource in &self.missing
+        {
+            writeln!( f, "// Missing: {} (from {})", resource.path, resource.source )?;
+        }
+
+        Ok( () )
+    }
+}
+/// A BSP and the resource list built for it.
+pub type BuiltRes = (PathBuf, io::Result<ResFile>);
+/// Builds the resource lists for the BSPs in a folder that pass the whitelist, paired with the BSP they are for,
+/// along with reports of the BSPs skipped for not being valid maps. Stock content is whatever is in the `svencoop` folder from the config.
+pub fn build_res(maps_dir: &Path, whitelist: &[String]) -> io::Result<(Vec<BuiltRes>, Vec<FileReport>)>
+{
+    let bsps = filter_bsps( load_bsps( maps_dir ), whitelist );
+
+    if bsps.is_empty()
+    {
+        return Err( io::Error::new( io::ErrorKind::NotFound, "No matching BSP files found." ) );
+    }
+
+    let ( bsps, skipped ) = check_maps( bsps );
+    let stock_dir = Config::get().svencoopdir.clone();
+
+    let res_files = bsps
+        .into_iter()
+        .map( |bsp|
+        {
+            let res = ResFile::build( &bsp, stock_dir.as_deref() );
+            ( bsp, res )
+        })
+    .collect();
+
+    Ok( ( res_files, skipped ) )
+}
+/// Writes a `.res` file for each BSP in the folder that passes the whitelist, backing up the old ones first.
+pub fn create_res(maps_dir: &Path, whitelist: &[String]) -> io::Result<Report>
+{
+    create_res_with_backup( maps_dir, whitelist, &BackupStore::open() )
+}
+/// Same as `create_res`, backing the files up into the given store.
+pub fn create_res_with_backup(maps_dir: &Path, whitelist: &[String], store: &BackupStore) -> io::Result<Report>
+{
+    let ( res_files, skipped ) = build_res( maps_dir, whitelist )?;
+    let targets: Vec<_> = res_files.iter().filter_map( |( _, res )| Some( res.as_ref().ok()?.path.clone() ) ).collect();
+
+    let set = store.snapshot( "generate RES", &targets )?;
+
+    let mut report: Report = res_files
+        .into_iter()
+        .map( |( bsp, res )| match res
+        {
+            Ok( res ) => FileReport::from_result( res.path.clone(), write_res( &res ) ),
+            Err( e ) => FileReport::new( bsp, Outcome::Failed( e ) )
+        })
+    .collect();
+
+    store.keep( set, &report )?;
+    report.extend( skipped );
+    Ok( report )
+}
+
+fn write_res(res: &ResFile) -> io::Result<Outcome>
+{
+    if res.resources.is_empty()
+    {
+        return Ok( Outcome::Skipped( "No custom content".into() ) );
+    }
+
+    let content = res.to_string();
+
+    if fs::read_to_string( &res.path ).is_ok_and( |before| before == content )
+    {
+        return Ok( Outcome::Skipped( "Already up to date".into() ) );
+    }
+
+    write_atomic( &res.path, content ).map( |()| Outcome::Written )
+}
@@ -0,0 +1,297 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    collections::HashMap,
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs,
+    path::Path,
+    sync::OnceLock
+};
+
+use serde::
+{
+    Deserialize,
+    Serialize
+};
+
+use crate::
+{
+    cfgdoc::Cvar,
+    config::
+    {
+        self,
+        Config
+    },
+    cvar::
+    {
+        get_default_cvars,
+        get_skill_cvars
+    }
+};
+
+const SCHEMA_FILE: &str = "cvar_schema.json";
+/// Schema shipped with the app, the user's own schema file is layered on top of it.
+const BUNDLED_SCHEMA: &str = include_str!( "cvar_schema.json" );
+
+static SCHEMA: OnceLock<Schema> = OnceLock::new();
+
+#[derive( Clone, Copy, Debug, PartialEq, Serialize, Deserialize )]
+#[serde( rename_all = "lowercase" )]
+pub enum CvarType
+{
+    Bool,
+    Int,
+    Float,
+    String,
+    Path
+}
+
+impl CvarType
+{   /// Guesses the type from a value in the default settings. Whole number defaults are taken as any number,
+    /// as plenty of CVars defaulting to one take fractions too.
+    fn infer(value: &str) -> Self
+    {
+        if value.parse::<f64>().is_ok()
+        {
+            Self::Float
+        }
+        else if value.contains( '/' ) || Path::new( value ).extension().is_some()
+        {
+            Self::Path
+        }
+        else
+        {
+            Self::String
+        }
+    }
+}
+
+impl Display for CvarType
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::Bool => write!( f, "0 or 1" ),
+            Self::Int => write!( f, "a whole number" ),
+            Self::Float => write!( f, "a number" ),
+            Self::String => write!( f, "text" ),
+            Self::Path => write!( f, "a relative file path" )
+        }
+    }
+}
+/// What is known about a CVar: the type of value it takes, its range, default and what it does.
+#[derive( Clone, Debug, Serialize, Deserialize )]
+pub struct CvarSpec
+{
+    #[serde( rename = "type" )]
+    pub kind: CvarType,
+    #[serde( default, skip_serializing_if = "Option::is_none" )]
+    pub min: Option<f64>,
+    #[serde( default, skip_serializing_if = "Option::is_none" )]
+    pub max: Option<f64>,
+    #[serde( default, skip_serializing_if = "Option::is_none" )]
+    pub default: Option<String>,
+    #[serde( default )]
+    pub description: String,
+    /// Whether the type comes from a schema file rather than being guessed from the default value.
+    /// Only declared types are trusted enough to refuse a write over.
+    #[serde( skip )]
+    pub declared: bool
+}
+
+impl CvarSpec
+{
+    fn new(kind: CvarType, default: Option<String>) -> Self
+    {
+        Self { kind, min: None, max: None, default, description: String::new(), declared: false }
+    }
+    /// Checks a value against the type and range, returns why it is invalid.
+    pub fn validate(&self, value: &str) -> Result<(), String>
+    {
+        let number =
+        match self.kind
+        {
+            CvarType::Bool if value == "0" || value == "1" => return Ok( () ),
+            CvarType::Int => value.parse::<i64>().ok().map( |n| n as f64 ),
+            CvarType::Float => value.parse::<f64>().ok().filter( |n| n.is_finite() ),
+            CvarType::String => return Ok( () ),
+            CvarType::Path if !value.contains( ':' ) && !value.starts_with( ['/', '\\'] ) => return Ok( () ),
+            _ => None
+        };
+
+        let Some( number ) = number
+        else
+        {
+            return Err( format!( "'{value}' is not {}", self.kind ) );
+        };
+
+        match ( self.min, self.max )
+        {
+            ( Some( min ), _ ) if number < min => Err( format!( "{value} is below the minimum of {min}" ) ),
+            ( _, Some( max ) ) if number > max => Err( format!( "{value} is above the maximum of {max}" ) ),
+            _ => Ok( () )
+        }
+    }
+}
+/// Registry of every known CVar and its metadata.
+#[derive( Debug, Default )]
+pub struct Schema
+{
+    specs: HashMap<String, CvarSpec>,
+    /// Whether the default settings were found, without them most real CVars are unknown.
+    complete: bool
+}
+
+impl Schema
+{   /// Builds the registry from CVar lines such as those in default_map_settings.cfg,
+    /// with types, ranges and descriptions from schema files overriding the guessed ones.
+    /// Entries named `prefix*` in a schema file describe all known CVars starting with the prefix.
+    pub fn build<'a>(cvar_lines: impl IntoIterator<Item = &'a String>, schema_files: &[&str]) -> Self
+    {
+        let mut specs = HashMap::new();
+
+        for line in cvar_lines
+        {
+            let mut tokens = line.split_whitespace();
+
+            let Some( name ) = tokens.next()
+            else
+            {
+                continue;
+            };
+
+            let default = tokens.next().map( |v| v.trim_matches( '"' ).to_owned() );
+            let kind = default.as_deref().map_or( CvarType::String, CvarType::infer );
+
+            specs.entry( name.to_ascii_lowercase() ).or_insert( CvarSpec::new( kind, default ) );
+        }
+
+        let mut patterns: Vec<(String, CvarSpec)> = vec![];
+
+        for file in schema_files
+        {
+            let overrides: HashMap<String, CvarSpec> =
+            match serde_json::from_str( file )
+            {
+                Ok( overrides ) => overrides,
+                Err( e ) =>
+                {
+                    eprintln!( "Failed to read CVar schema: {e}" );
+                    continue;
+                }
+            };
+
+            for ( name, mut spec ) in overrides
+            {
+                let name = name.to_ascii_lowercase();
+                spec.declared = true;
+
+                if let Some( prefix ) = name.strip_suffix( '*' )
+                {
+                    patterns.push( ( prefix.to_owned(), spec ) );
+                }
+                else
+                {
+                    Self::merge( &mut specs, name, spec );
+                }
+            }
+        }
+        // Longest prefix wins
+        patterns.sort_by_key( |( prefix, _ )| prefix.len() );
+
+        for ( name, spec ) in specs.iter_mut()
+        {
+            if let Some( ( _, pattern ) ) = patterns.iter().rev().find( |( prefix, _ )| name.starts_with( prefix.as_str() ) )
+            && spec.description.is_empty()
+            {
+                *spec = CvarSpec { default: spec.default.take().or( pattern.default.clone() ), ..pattern.clone() };
+            }
+        }
+
+        Self { specs, complete: true }
+    }
+    /// Overrides a spec, keeping the known default if the override does not give one.
+    fn merge(specs: &mut HashMap<String, CvarSpec>, name: String, spec: CvarSpec)
+    {
+        let default = specs.get( &name ).and_then( |s| s.default.clone() );
+        specs.insert( name, CvarSpec { default: spec.default.or( default ), ..spec } );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CvarSpec>
+    {
+        self.specs.get( &name.to_ascii_lowercase() )
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str>
+    {
+        self.specs.keys().map( String::as_str )
+    }
+    /// Marks the registry as built without the default settings, so a name missing from it is not taken as a mistake.
+    pub fn without_defaults(self) -> Self
+    {
+        Self { complete: false, ..self }
+    }
+    /// Whether every CVar the game has is known, so a name missing from the schema is a mistake.
+    pub fn is_complete(&self) -> bool
+    {
+        self.complete
+    }
+    /// Checks a CVar's value against a type declared in a schema file. Unknown CVars, guessed types
+    /// and bare flags without a value are not checked.
+    pub fn validate(&self, cvar: &Cvar) -> Result<(), String>
+    {
+        match self.get( &cvar.name )
+        {
+            Some( spec ) if spec.declared && !cvar.value.is_empty() => spec
+                .validate( &cvar.value )
+            .map_err( |e| format!( "{}: {e}", cvar.name ) ),
+            _ => Ok( () )
+        }
+    }
+}
+/// Gets the CVar registry, built from the default and skill settings, the bundled schema and the user's schema file.
+pub fn schema() -> &'static Schema
+{
+    SCHEMA.get_or_init( ||
+    {
+        let user_schema_path = config::appdata_base().join( SCHEMA_FILE );
+        let user_schema = fs::read_to_string( &user_schema_path ).unwrap_or_default();
+
+        let mut schema_files = vec![BUNDLED_SCHEMA];
+
+        if !user_schema.trim().is_empty()
+        {
+            schema_files.push( &user_schema );
+        }
+
+        let cvar_lines = get_default_cvars()
+            .iter()
+            .chain( get_skill_cvars() )
+        .chain( &Config::get().additional_cvars );
+
+        let schema = Schema::build( cvar_lines, &schema_files );
+
+        if get_default_cvars().is_empty() { schema.without_defaults() } else { schema }
+    })
+}
@@ -0,0 +1,172 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs::File,
+    io::
+    {
+        self,
+        Read
+    },
+    path::Path
+};
+
+use strum::FromRepr;
+
+pub const EXT_SPR: &str = "spr";
+pub const SPR_VERSION: i32 = 2;
+const SPR_ID: &[u8; 4] = b"IDSP";
+const HEADER_SIZE: usize = 40;
+/// How the sprite faces the player.
+#[repr( i32 )]
+#[derive( Clone, Copy, Debug, PartialEq, strum::Display, FromRepr )]
+pub enum SpriteType
+{
+    #[strum( to_string = "vp_parallel_upright" )]
+    ParallelUpright,
+    #[strum( to_string = "facing_upright" )]
+    FacingUpright,
+    #[strum( to_string = "vp_parallel" )]
+    Parallel,
+    #[strum( to_string = "oriented" )]
+    Oriented,
+    #[strum( to_string = "vp_parallel_oriented" )]
+    ParallelOriented
+}
+/// How the sprite's palette is drawn.
+#[repr( i32 )]
+#[derive( Clone, Copy, Debug, PartialEq, strum::Display, FromRepr )]
+pub enum TextureFormat
+{
+    #[strum( to_string = "normal" )]
+    Normal,
+    #[strum( to_string = "additive" )]
+    Additive,
+    #[strum( to_string = "indexalpha" )]
+    IndexAlpha,
+    #[strum( to_string = "alphatest" )]
+    AlphaTest
+}
+/// Header of a GoldSrc (v2) sprite.
+#[derive( Clone, Debug )]
+pub struct Spr
+{
+    pub kind: SpriteType,
+    pub format: TextureFormat,
+    pub frames: usize,
+    /// Size of the largest frame.
+    pub width: u32,
+    pub height: u32
+}
+
+impl Spr
+{   /// Reads the header without loading the frames.
+    pub fn read(path: &Path) -> io::Result<Self>
+    {
+        let mut bytes = [0u8; HEADER_SIZE];
+        File::open( path )?.read_exact( &mut bytes ).map_err( |_| invalid( "File is too small to be a sprite." ) )?;
+
+        Self::from_bytes( &bytes )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self>
+    {
+        if bytes.len() < HEADER_SIZE
+        {
+            return Err( invalid( "File is too small to be a sprite." ) );
+        }
+
+        if &bytes[..4] != SPR_ID
+        {
+            return Err( invalid( "Not a GoldSrc sprite." ) );
+        }
+
+        let read_i32 = |at: usize| i32::from_le_bytes( [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]] );
+        let version = read_i32( 4 );
+
+        if version != SPR_VERSION
+        {
+            return Err( invalid( &format!( "Unsupported SPR version {version}, expected {SPR_VERSION}." ) ) );
+        }
+
+        let kind = SpriteType::from_repr( read_i32( 8 ) ).ok_or_else( || invalid( &format!( "Unknown sprite type {}.", read_i32( 8 ) ) ) )?;
+        let format = TextureFormat::from_repr( read_i32( 12 ) ).ok_or_else( || invalid( &format!( "Unknown texture format {}.", read_i32( 12 ) ) ) )?;
+
+        Ok( Self
+        {
+            kind,
+            format,
+            frames: read_i32( 28 ).max( 0 ) as usize,
+            width: read_i32( 20 ).max( 0 ) as u32,
+            height: read_i32( 24 ).max( 0 ) as u32
+        })
+    }
+    /// Differences from the new sprite that change how maps using the original look. `env_sprite` framerates
+    /// are set for the original's frames, and the type and format decide how it is drawn.
+    pub fn compatibility(&self, new: &Self) -> Vec<String>
+    {
+        let mut warnings = vec![];
+
+        if new.frames != self.frames
+        {
+            warnings.push( format!( "Has {} frame(s), the original has {}", new.frames, self.frames ) );
+        }
+
+        if new.kind != self.kind
+        {
+            warnings.push( format!( "Is {}, the original is {}", new.kind, self.kind ) );
+        }
+
+        if new.format != self.format
+        {
+            warnings.push( format!( "Is drawn {}, the original is drawn {}", new.format, self.format ) );
+        }
+
+        warnings
+    }
+}
+
+impl Display for Spr
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!( f, "{}, {}, {} frame(s), {}x{}", self.kind, self.format, self.frames, self.width, self.height )
+    }
+}
+/// Checks a sprite replacement for differences from the original. Files that can't be read are warned about too.
+pub fn replacement_warnings(original: &Path, new: &Path) -> Vec<String>
+{
+    let read = |path: &Path| Spr::read( path ).map_err( |e| format!( "Could not read {}: {e}", path.display() ) );
+
+    match ( read( original ), read( new ) )
+    {
+        ( Ok( o ), Ok( n ) ) => o.compatibility( &n ),
+        ( Err( e ), Ok( _ ) ) | ( Ok( _ ), Err( e ) ) => vec![e],
+        ( Err( a ), Err( b ) ) => vec![a, b]
+    }
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new( io::ErrorKind::InvalidData, message.to_owned() )
+}
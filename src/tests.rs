@@ -0,0 +1,864 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fs,
+    path::
+    {
+        Path,
+        PathBuf
+    }
+};
+
+use crate::
+{
+    audit::Audit,
+    backup::BackupStore,
+    bsp::
+    {
+        BSP_VERSION,
+        Bsp
+    },
+    casecheck::CaseCheck,
+    cli::
+    {
+        Args,
+        is_command
+    },
+    cfgdoc::
+    {
+        CfgDocument,
+        Cvar,
+        Entry
+    },
+    cvar::
+    {
+        Cfg,
+        WriteType,
+        create_motd
+    },
+    diff::
+    {
+        FileChange,
+        unified_diff
+    },
+    lint::
+    {
+        Severity,
+        lint
+    },
+    materials::read_texture_names,
+    mdl::Mdl,
+    package::package_map,
+    replacements::
+    {
+        GameRoots,
+        MatchRules,
+        Replacement
+    },
+    report::Outcome,
+    rescheck::ResourceCheck,
+    resources::ResFile,
+    schema::Schema,
+    transaction::Transaction,
+    wad::
+    {
+        LumpKind,
+        Wad,
+        mip_for
+    },
+    wav::Wav
+};
+
+/// A BSP v30 header with every lump empty.
+fn empty_map() -> Vec<u8>
+{
+    let mut header = BSP_VERSION.to_le_bytes().to_vec();
+    header.resize( 4 + 15 * 8, 0 );
+
+    header
+}
+/// A BSP v30 with the given entity lump and every other lump empty.
+fn map_with_entities(entities: &str) -> Vec<u8>
+{
+    let mut map = empty_map();
+    let offset = map.len() as u32;
+    map[4..8].copy_from_slice( &offset.to_le_bytes() );
+    map[8..12].copy_from_slice( &( entities.len() as u32 ).to_le_bytes() );
+    map.extend_from_slice( entities.as_bytes() );
+
+    map
+}
+
+/// A v10 model header with the given sequences, skin families and textures, followed by its sequence table.
+fn model(sequences: &[&str], skins: i32, textures: i32) -> Vec<u8>
+{
+    let mut model = vec![0u8; 244];
+    let mut put = |at: usize, value: i32| model[at..at + 4].copy_from_slice( &value.to_le_bytes() );
+
+    put( 4, 10 );
+    put( 164, sequences.len() as i32 );
+    put( 168, 244 );
+    put( 172, 1 );
+    put( 180, textures );
+    put( 196, skins );
+    model[..4].copy_from_slice( b"IDST" );
+    model[8..12].copy_from_slice( b"test" );
+
+    for sequence in sequences
+    {
+        let mut entry = sequence.as_bytes().to_vec();
+        entry.resize( 176, 0 );
+        model.extend( entry );
+    }
+
+    model
+}
+
+#[test]
+fn cfg_round_trip()
+{
+    let texts =
+    [
+        "",
+        "mp_flashlight 1",
+        "mp_flashlight 1\n",
+        "mp_flashlight 1\r\nmp_timelimit 30\r\n",
+        "mp_flashlight 1\r\nmp_timelimit 30",
+        "\u{FEFF}mp_flashlight 1\n",
+        "// header\n\n# old style\nmp_flashlight 1 // on\n  spaced   out  \n",
+        "hostname \"my server\" // name\nmotd \"a // b\"\n\"bad\" 1\nmp_x \"open\n"
+    ];
+
+    for text in texts
+    {
+        assert_eq!( CfgDocument::parse( text ).to_string(), text );
+    }
+}
+
+#[test]
+fn cfg_bom_is_not_part_of_the_name()
+{
+    let mut doc = CfgDocument::parse( "\u{FEFF}mp_flashlight 1\r\n" );
+
+    assert_eq!( doc.get( "mp_flashlight" ).map( |c| c.value.as_str() ), Some( "1" ) );
+
+    doc.set( Cvar::new( "mp_flashlight", "0" ) );
+    assert_eq!( doc.to_string(), "\u{FEFF}mp_flashlight 0\r\n" );
+}
+
+#[test]
+fn cfg_from_file_reads_non_utf8()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join( "map.cfg" );
+    let bytes = b"// caf\xe9 \x80\r\nmp_flashlight 1\r\n";
+    fs::write( &path, bytes ).unwrap();
+
+    let doc = CfgDocument::from_file( &path ).unwrap();
+
+    assert_eq!( doc.entries().next(), Some( &Entry::Comment( "// caf\u{e9} \u{80}".to_owned() ) ) );
+    assert_eq!( doc.get( "mp_flashlight" ).map( |c| c.value.as_str() ), Some( "1" ) );
+    assert_eq!( doc.to_bytes().unwrap(), bytes );
+    // Writing a CVar through a write mode keeps the other lines' bytes too
+    WriteType::SET.preview( &path, "mp_flashlight 0\n" ).unwrap().write().unwrap();
+    assert_eq!( fs::read( &path ).unwrap(), b"// caf\xe9 \x80\r\nmp_flashlight 0\r\n" );
+}
+
+#[test]
+fn lint_without_default_cvars()
+{
+    let schema = Schema::build( &Vec::new(), &[] ).without_defaults();
+    let diagnostics = lint( "mp_flashlight 1\nsv_gravity 800\n", &schema );
+
+    assert_eq!( diagnostics.len(), 1 );
+    assert_eq!( diagnostics[0].severity, Severity::Warning );
+}
+
+#[test]
+fn guessed_types_only_warn()
+{
+    let lines = vec!["sv_gravity 800".to_owned(), "mp_flashlight 0".to_owned()];
+    let schema = Schema::build( &lines, &[r#"{ "mp_flashlight": { "type": "bool" } }"#] );
+
+    assert!( schema.validate( &Cvar::new( "sv_gravity", "800.5" ) ).is_ok() );
+    assert!( schema.validate( &Cvar::new( "sv_gravity", "fast" ) ).is_ok() );
+    assert!( schema.validate( &Cvar::new( "mp_flashlight", "2" ) ).is_err() );
+
+    let severities: Vec<_> = lint( "sv_gravity 800.5\nsv_gravity2 1\nsv_gravity fast\nmp_flashlight 2\n", &schema )
+        .into_iter()
+        .filter( |d| d.message.starts_with( "Invalid value" ) )
+        .map( |d| d.severity )
+    .collect();
+
+    assert_eq!( severities, vec![Severity::Warning, Severity::Error] );
+}
+
+#[test]
+fn backup_records_full_paths()
+{
+    let store_dir = tempfile::tempdir().unwrap();
+    let store = BackupStore::new( store_dir.path().to_path_buf(), 1 );
+
+    let set = store.snapshot( "test", &[PathBuf::from( "not_written_yet.cfg" )] ).unwrap();
+    let original = &set.manifest.files[0].original;
+
+    assert!( original.is_absolute() );
+    assert_eq!( *original, std::env::current_dir().unwrap().canonicalize().unwrap().join( "not_written_yet.cfg" ) );
+    assert!( set.manifest.files[0].backup.is_none() );
+}
+
+#[test]
+fn invalid_maps_are_skipped()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let header = empty_map();
+
+    fs::write( dir.path().join( "good.bsp" ), &header ).unwrap();
+    fs::write( dir.path().join( "cut.bsp" ), &header[..20] ).unwrap();
+    fs::write( dir.path().join( "quake.bsp" ), [&29i32.to_le_bytes(), &header[4..]].concat() ).unwrap();
+
+    let report = create_motd( "Welcome", dir.path() ).unwrap();
+
+    assert_eq!( report.succeeded(), 1 );
+    assert!( dir.path().join( "good_motd.txt" ).exists() );
+    assert!( !dir.path().join( "cut_motd.txt" ).exists() && !dir.path().join( "quake_motd.txt" ).exists() );
+
+    for name in ["cut.bsp", "quake.bsp"]
+    {
+        let file = report.files.iter().find( |f| f.path.ends_with( name ) ).unwrap();
+        assert!( matches!( &file.outcome, Outcome::Skipped( reason ) if reason.starts_with( "Not a valid map" ) ) );
+    }
+}
+
+#[test]
+fn package_streams_to_the_archive()
+{
+    let maps = tempfile::tempdir().unwrap();
+    let out = tempfile::tempdir().unwrap();
+    let bsp = maps.path().join( "m1.bsp" );
+    fs::write( &bsp, empty_map() ).unwrap();
+    fs::write( maps.path().join( "m1.cfg" ), "hostname x\n" ).unwrap();
+
+    let manifest = package_map( &bsp, out.path(), None ).unwrap();
+    let mut zip = zip::ZipArchive::new( fs::File::open( out.path().join( "m1.zip" ) ).unwrap() ).unwrap();
+    let names: Vec<String> = zip.file_names().map( |name| name.unwrap().into_owned() ).collect();
+
+    assert_eq!( manifest.files.len(), 2 );
+    assert_eq!( names.len(), 3 );
+    assert!( names.contains( &"maps/m1.bsp".to_owned() ) && names.contains( &"maps/m1.cfg".to_owned() ) );
+    assert_eq!( zip.by_name( "maps/m1.bsp" ).unwrap().compression(), zip::CompressionMethod::Deflated );
+    // The manifest is inside the archive, not next to it
+    let stored: serde_json::Value = serde_json::from_reader( zip.by_name( "m1_manifest.json" ).unwrap() ).unwrap();
+    assert_eq!( stored["files"].as_array().unwrap().len(), 2 );
+    assert_eq!( stored["files"][0]["crc32"], format!( "{:08x}", crc32fast::hash( &fs::read( &bsp ).unwrap() ) ) );
+    assert!( !out.path().join( "m1_manifest.json" ).exists() );
+    assert!( !out.path().join( "m1.zip.tmp" ).exists() );
+}
+
+#[test]
+fn case_fix_in_replacement_files()
+{
+    let game = tempfile::tempdir().unwrap();
+    let ( maps, models ) = ( game.path().join( "maps" ), game.path().join( "models/m1" ) );
+    fs::create_dir_all( &maps ).unwrap();
+    fs::create_dir_all( &models ).unwrap();
+
+    fs::write( maps.join( "m1.bsp" ), empty_map() ).unwrap();
+    fs::write( maps.join( "m1.cfg" ), "globalmodellist m1.gmr\n" ).unwrap();
+    fs::write( models.join( "barney2.mdl" ), "" ).unwrap();
+    fs::write( models.join( "sci.mdl" ), "" ).unwrap();
+    fs::write( models.join( "m1.gmr" ),
+        "// models/m1/Barney2.mdl\r\nmodels/barney.mdl   models/m1/Barney2.mdl // note\r\n\" models/scientist.mdl \" \" models/m1/Sci.mdl\"\r\n" ).unwrap();
+
+    let check = CaseCheck::run_in( None, &maps, &[] ).unwrap();
+    let references: Vec<_> = check.issues.iter().map( |i| i.reference.as_str() ).collect();
+    assert_eq!( references, ["models/m1/Barney2.mdl", "models/m1/Sci.mdl"] );
+
+    let store_dir = tempfile::tempdir().unwrap();
+    check.fix_with_backup( &BackupStore::new( store_dir.path().to_path_buf(), 1 ) ).unwrap();
+
+    assert_eq!( fs::read_to_string( models.join( "m1.gmr" ) ).unwrap(),
+        "// models/m1/Barney2.mdl\r\nmodels/barney.mdl   models/m1/barney2.mdl // note\r\n\" models/scientist.mdl \" \" models/m1/sci.mdl\"\r\n" );
+}
+
+#[test]
+fn models_and_sprites_do_not_mix()
+{
+    let roots = GameRoots::default();
+
+    assert!( Replacement::try_new_in( &roots, "models/a.mdl", "models/b.mdl" ).is_ok() );
+    assert!( Replacement::try_new_in( &roots, "sprites/a.spr", "sprites/b.spr" ).is_ok() );
+    assert!( Replacement::try_new_in( &roots, "models/a.mdl", "sprites/b.spr" ).is_err() );
+    assert!( Replacement::try_new_in( &roots, "sprites/a.spr", "models/b.mdl" ).is_err() );
+
+    let parsed = Replacement::parse( "models/a.mdl sprites/b.spr\n", &roots );
+    assert!( parsed.replacements.is_empty() );
+    assert_eq!( parsed.diagnostics[0].severity, Severity::Error );
+}
+
+#[test]
+fn wav_with_an_oversized_chunk()
+{
+    let mut bytes = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+    bytes.extend( 16u32.to_le_bytes() );
+    for field in [1u16, 1, 0x5622, 0, 0x5622, 0, 1, 8]
+    {
+        bytes.extend( field.to_le_bytes() );
+    }
+    // A data chunk claiming far more than the file holds, as truncated files do
+    bytes.extend( b"data" );
+    bytes.extend( u32::MAX.to_le_bytes() );
+    bytes.extend( [0x80; 10] );
+
+    let wav = Wav::from_bytes( &bytes ).unwrap();
+
+    assert_eq!( ( wav.sample_rate, wav.bits, wav.data_len ), ( 22050, 8, 10 ) );
+}
+
+#[test]
+fn mip_level_never_below_the_size()
+{
+    assert_eq!( mip_for( 256, 256, 64 ), 2 );
+    assert_eq!( mip_for( 256, 128, 64 ), 2 );
+    assert_eq!( mip_for( 96, 32, 64 ), 0 );
+    assert_eq!( mip_for( 128, 16, 64 ), 1 );
+    assert_eq!( mip_for( 1024, 1024, 64 ), 3 );
+    assert_eq!( mip_for( 16, 16, 64 ), 0 );
+}
+
+#[test]
+fn lint_positions_count_characters()
+{
+    let lines = vec!["mp_flashlight 0".to_owned()];
+    let schema = Schema::build( &lines, &[] );
+    let text = "\u{FEFF}  mp_flashlite 1\r\n// caf\u{e9}\n\u{a0}\u{a0}mp_flashlite 1\n\"bad\" 1\n";
+
+    let positions: Vec<_> = lint( text, &schema ).iter().map( |d| ( d.line, d.column ) ).collect();
+
+    assert_eq!( positions, [( 1, 3 ), ( 3, 3 ), ( 3, 3 ), ( 4, 1 )] );
+}
+
+#[test]
+fn diff_hunks_keep_three_lines_of_context()
+{
+    let old: String = ( 1..=12 ).map( |n| format!( "{n}\n" ) ).collect();
+    let new: String = ( 1..=12 )
+        .map( |n|
+        match n
+        {
+            2 => "two\n".to_owned(),
+            11 => "eleven\n".to_owned(),
+            _ => format!( "{n}\n" )
+        })
+    .collect();
+
+    assert_eq!( unified_diff( "a", "b", &old, &old ), "" );
+    assert_eq!( unified_diff( "a", "b", &old, &new ),
+        "--- a\n+++ b\n@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -8,5 +8,5 @@\n 8\n 9\n 10\n-11\n+eleven\n 12\n" );
+}
+
+#[test]
+fn diff_shows_line_ending_changes()
+{
+    assert_eq!( unified_diff( "a", "b", "x\r\ny\r\n", "x\ny\n" ), "--- a\n+++ b\n@@ -1,2 +1,2 @@\n-x\r\n-y\r\n+x\n+y\n" );
+    assert_eq!( unified_diff( "a", "b", "x\ny", "x\ny\n" ), "--- a\n+++ b\n@@ -1,2 +1,2 @@\n x\n-y\n\\ No newline at end of file\n+y\n" );
+}
+
+#[test]
+fn stale_preview_is_not_applied()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join( "map.cfg" );
+    fs::write( &path, "mp_flashlight 1\n" ).unwrap();
+
+    let cfg = Cfg
+    {
+        cvars: "mp_flashlight 0".into(),
+        writetype: WriteType::SET,
+        is_skillcfg: false,
+        bspdir: dir.path().to_path_buf(),
+        bspwhitelist: vec![]
+    };
+    let store = BackupStore::new( dir.path().join( "backups" ), 5 );
+    let changes = vec![WriteType::SET.preview( &path, "mp_flashlight 0\n" ).unwrap()];
+    fs::write( &path, "mp_timelimit 30\n" ).unwrap();
+
+    let report = cfg.apply_with_backup( changes, &store, false ).unwrap();
+
+    assert!( report.has_failures() );
+    assert_eq!( fs::read_to_string( &path ).unwrap(), "mp_timelimit 30\n" );
+}
+
+#[test]
+fn backups_only_hold_changed_files()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let ( same, changed ) = ( dir.path().join( "same.cfg" ), dir.path().join( "changed.cfg" ) );
+    fs::write( &same, "mp_flashlight 0\n" ).unwrap();
+    fs::write( &changed, "mp_flashlight 1\n" ).unwrap();
+
+    let cfg = Cfg
+    {
+        cvars: "mp_flashlight 0".into(),
+        writetype: WriteType::SET,
+        is_skillcfg: false,
+        bspdir: dir.path().to_path_buf(),
+        bspwhitelist: vec![]
+    };
+    let store = BackupStore::new( dir.path().join( "backups" ), 5 );
+    let preview = |paths: &[&PathBuf]| paths.iter().map( |p| WriteType::SET.preview( p, "mp_flashlight 0\n" ).unwrap() ).collect();
+
+    cfg.apply_with_backup( preview( &[&same, &changed] ), &store, false ).unwrap();
+    let sets = store.sets().unwrap();
+    assert_eq!( sets.len(), 1 );
+    assert_eq!( sets[0].manifest.files.len(), 1 );
+    assert!( sets[0].manifest.files[0].original.ends_with( "changed.cfg" ) );
+    // Nothing left to change, so no new set
+    cfg.apply_with_backup( preview( &[&same, &changed] ), &store, false ).unwrap();
+    assert_eq!( store.sets().unwrap().len(), 1 );
+    // A rolled back write leaves no set behind
+    fs::write( &changed, "mp_flashlight 1\n" ).unwrap();
+    let changes = preview( &[&changed] );
+    fs::write( &changed, "mp_timelimit 30\n" ).unwrap();
+
+    assert!( cfg.apply_with_backup( changes, &store, true ).is_err() );
+    assert_eq!( store.sets().unwrap().len(), 1 );
+}
+
+#[test]
+fn res_leaves_out_stock_and_lists_missing_cfg_files()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let ( stock, maps ) = ( dir.path().join( "svencoop" ), dir.path().join( "svencoop_addon/maps" ) );
+    fs::create_dir_all( stock.join( "models" ) ).unwrap();
+    fs::create_dir_all( &maps ).unwrap();
+    fs::write( stock.join( "models/Stock.mdl" ), "" ).unwrap();
+
+    let bsp = maps.join( "test.bsp" );
+    fs::write( &bsp, map_with_entities( "{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"cycler\"\n\"model\" \"models/stock.mdl\"\n}\n{\n\"classname\" \"cycler\"\n\"model\" \"models/custom.mdl\"\n}\n" ) ).unwrap();
+    fs::write( maps.join( "test.cfg" ), "globalmodellist nowhere.gmr\n" ).unwrap();
+
+    let res = ResFile::build( &bsp, Some( &stock ) ).unwrap();
+    let paths: Vec<_> = res.resources.iter().map( |r| r.path.as_str() ).collect();
+
+    assert_eq!( paths, ["models/custom.mdl"] );
+    assert_eq!( res.missing.len(), 1 );
+    assert_eq!( res.missing[0].path, "nowhere.gmr" );
+    assert!( res.to_string().contains( "// Missing: nowhere.gmr (from globalmodellist)" ) );
+}
+
+#[test]
+fn game_roots_resolve_paths()
+{
+    let roots = GameRoots { roots: vec![PathBuf::from( "C:/Games/Sven Co-op/svencoop_addon" )] };
+
+    assert_eq!( roots.resolve( "models/barney.mdl" ).unwrap(), "models/barney.mdl" );
+    assert_eq!( roots.resolve( "C:\\Games\\Sven Co-op\\svencoop_addon\\models\\m1\\barney.mdl" ).unwrap(), "models/m1/barney.mdl" );
+    assert_eq!( roots.resolve( "C:/Games/Sven Co-op/svencoop_addon/sound/m1/door.wav" ).unwrap(), "m1/door.wav" );
+    assert_eq!( roots.resolve( "sound/m1/door.wav" ).unwrap(), "m1/door.wav" );
+    assert_eq!( roots.resolve( "m1/door.wav" ).unwrap(), "m1/door.wav" );
+    // Another install's content folder still gives the game path
+    assert_eq!( roots.resolve( "D:/backup/svencoop_hd/models/hgrunt.mdl" ).unwrap(), "models/hgrunt.mdl" );
+    // A folder named like a game folder is not a content folder
+    assert!( roots.resolve( "C:/mysounds/models/barney.mdl" ).is_err() );
+    assert!( roots.resolve( "C:/Games/Sven Co-op/svencoop_addon/maps/barney.mdl" ).is_err() );
+    assert!( roots.resolve( "models/../barney.mdl" ).is_err() );
+    assert!( roots.resolve( "models/readme.txt" ).is_err() );
+}
+
+#[test]
+fn case_fix_keeps_cfg_lines_and_file_bytes()
+{
+    let game = tempfile::tempdir().unwrap();
+    let ( maps, models ) = ( game.path().join( "maps" ), game.path().join( "models/m1" ) );
+    fs::create_dir_all( &maps ).unwrap();
+    fs::create_dir_all( &models ).unwrap();
+
+    fs::write( maps.join( "m1.bsp" ), empty_map() ).unwrap();
+    fs::write( maps.join( "m1.cfg" ), "globalmodellist   \"M1.gmr\"  // list\r\nglobalmodellist M1.gmr\r\n" ).unwrap();
+    fs::write( models.join( "barney2.mdl" ), "" ).unwrap();
+    fs::write( models.join( "m1.gmr" ), b"// caf\xe9\nmodels/a.mdl models/m1/Barney2.mdl\n" ).unwrap();
+
+    let check = CaseCheck::run_in( None, &maps, &[] ).unwrap();
+    let references: Vec<_> = check.issues.iter().map( |i| i.reference.as_str() ).collect();
+    assert_eq!( references, ["models/m1/Barney2.mdl", "M1.gmr"] );
+
+    let store_dir = tempfile::tempdir().unwrap();
+    check.fix_with_backup( &BackupStore::new( store_dir.path().to_path_buf(), 1 ) ).unwrap();
+    // Both lines keep their own spacing, quotes and comment
+    assert_eq!( fs::read_to_string( maps.join( "m1.cfg" ) ).unwrap(), "globalmodellist   \"m1.gmr\"  // list\r\nglobalmodellist m1.gmr\r\n" );
+    assert_eq!( fs::read( models.join( "m1.gmr" ) ).unwrap(), b"// caf\xe9\nmodels/a.mdl models/m1/barney2.mdl\n" );
+}
+
+#[test]
+fn match_dirs_pairs_by_path_then_name()
+{
+    let game = tempfile::tempdir().unwrap();
+    let ( addon, stock ) = ( game.path().join( "svencoop_addon" ), game.path().join( "svencoop" ) );
+    let roots = GameRoots { roots: vec![addon.clone(), stock.clone()] };
+
+    for file in ["models/old/barney.mdl", "models/old/lonely.mdl", "models/old/gun/scope.mdl", "models/new/barney_hd.mdl", "models/new/other/scope.mdl"]
+    {
+        let path = addon.join( file );
+        fs::create_dir_all( path.parent().unwrap() ).unwrap();
+        fs::write( path, "" ).unwrap();
+    }
+
+    let matched = Replacement::match_dirs( &addon.join( "models/old" ), &addon.join( "models/new" ), &MatchRules::default(), &roots );
+    let pairs: Vec<_> = matched.replacements.iter().map( |r| ( r.get_original(), r.get_new() ) ).collect();
+
+    assert_eq!( pairs, [( "models/old/barney.mdl", "models/new/barney_hd.mdl" ), ( "models/old/gun/scope.mdl", "models/new/other/scope.mdl" )] );
+    assert_eq!( matched.files[0], ( addon.join( "models/old/barney.mdl" ), addon.join( "models/new/barney_hd.mdl" ) ) );
+    assert_eq!( matched.unmatched, ["lonely.mdl"] );
+
+    let rules = MatchRules { by_name: false, ..MatchRules::default() };
+    let matched = Replacement::match_dirs( &addon.join( "models/old" ), &addon.join( "models/new" ), &rules, &roots );
+    assert_eq!( matched.replacements.len(), 1 );
+    assert_eq!( matched.unmatched.len(), 2 );
+    // The same game path in two content folders can't replace itself
+    fs::create_dir_all( stock.join( "models/old" ) ).unwrap();
+    fs::write( stock.join( "models/old/barney.mdl" ), "" ).unwrap();
+
+    let matched = Replacement::match_dirs( &stock, &addon, &MatchRules::default(), &roots );
+    assert!( matched.replacements.is_empty() );
+    assert_eq!( matched.same, [Path::new( "models/old/barney.mdl" ).to_string_lossy()] );
+}
+
+#[test]
+fn model_header_and_compatibility()
+{
+    let original = Mdl::from_bytes( &model( &["idle", "walk", "run"], 2, 1 ) ).unwrap();
+    assert_eq!( original.name, "test" );
+    assert_eq!( original.sequences, ["idle", "walk", "run"] );
+    assert_eq!( original.skins, Some( 2 ) );
+
+    let new = Mdl::from_bytes( &model( &["IDLE", "run"], 1, 1 ) ).unwrap();
+    assert_eq!( original.compatibility( &new ), ["Missing 1 sequence(s): walk", "Has 1 skin(s), the original has 2"] );
+
+    let mut broken = model( &["idle"], 1, 1 );
+    broken[164..168].copy_from_slice( &2i32.to_le_bytes() );
+    assert!( Mdl::from_bytes( &broken ).is_err() );
+    assert!( Mdl::from_bytes( b"IDSQ" ).is_err() );
+}
+
+#[test]
+fn model_skins_come_from_the_texture_model()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join( "barney.mdl" );
+    fs::write( &path, model( &["idle"], 0, 0 ) ).unwrap();
+    // Unknown without the texture model, so nothing is compared
+    let mdl = Mdl::read( &path ).unwrap();
+    assert_eq!( mdl.skins, None );
+    assert!( Mdl::from_bytes( &model( &["idle"], 3, 1 ) ).unwrap().compatibility( &mdl ).is_empty() );
+    assert_eq!( mdl.companions( &path ), [dir.path().join( "barneyT.mdl" )] );
+
+    fs::write( dir.path().join( "barneyT.mdl" ), model( &[], 4, 1 ) ).unwrap();
+    assert_eq!( Mdl::read( &path ).unwrap().skins, Some( 4 ) );
+}
+
+#[test]
+fn wad_directory_without_the_textures()
+{
+    // A 16x8 miptex with its name, size and mip offsets, and the directory after it
+    let mut miptex = b"brick".to_vec();
+    miptex.resize( 16, 0 );
+    miptex.extend( [16u32, 8, 40, 168, 200, 208].iter().flat_map( |v| v.to_le_bytes() ) );
+    miptex.resize( 210 + 2 + 768, 0 );
+
+    let mut wad = b"WAD3".to_vec();
+    wad.extend( [1u32, 12 + miptex.len() as u32].iter().flat_map( |v| v.to_le_bytes() ) );
+    wad.extend( &miptex );
+    wad.extend( [12u32, miptex.len() as u32, miptex.len() as u32].iter().flat_map( |v| v.to_le_bytes() ) );
+    wad.extend( [0x43, 0, 0, 0] );
+    let mut name = b"BRICK".to_vec();
+    name.resize( 16, 0 );
+    wad.extend( name );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join( "test.wad" );
+    fs::write( &path, &wad ).unwrap();
+
+    let lumps = Wad::read_directory( &path ).unwrap();
+    let whole = Wad::from_bytes( wad.clone() ).unwrap();
+    assert_eq!( ( lumps[0].kind, lumps[0].width, lumps[0].height ), ( LumpKind::MipTex, 16, 8 ) );
+    assert_eq!( ( whole.lumps[0].width, whole.lumps[0].height ), ( 16, 8 ) );
+    assert_eq!( read_texture_names( &path ).unwrap(), ["BRICK"] );
+    // A directory past the end of the file is refused, not read
+    let past_end = wad.len() as u32;
+    wad[8..12].copy_from_slice( &past_end.to_le_bytes() );
+    fs::write( &path, &wad ).unwrap();
+    assert!( Wad::read_directory( &path ).is_err() );
+}
+
+#[test]
+fn cli_options_and_files()
+{
+    let args: Vec<String> = ["--write-type", "SET", "-b", "c1a0, c1a1", "--bsp", "c1a2", "-s", "--dry-run", "-", "extra.cfg"]
+        .iter()
+        .map( |a| a.to_string() )
+    .collect();
+
+    let parsed = Args::parse( &args ).unwrap();
+    assert!( parsed.write_type == Some( WriteType::SET ) );
+    assert_eq!( parsed.bsps, ["c1a0", "c1a1", "c1a2"] );
+    assert!( parsed.skill && parsed.dry_run && !parsed.all_or_nothing );
+    assert_eq!( parsed.files, ["-", "extra.cfg"] );
+
+    let parse = |args: &[&str]| Args::parse( &args.iter().map( |a| a.to_string() ).collect::<Vec<_>>() ).map( |_| () ).unwrap_err().to_string();
+    assert_eq!( parse( &["--maps-dir"] ), "Missing value for '--maps-dir'." );
+    assert_eq!( parse( &["--write-type", "rewrite"] ), "Unknown write type 'rewrite'." );
+    assert_eq!( parse( &["--verbose"] ), "Unknown option '--verbose'." );
+
+    assert!( is_command( "cfg" ) && is_command( "-h" ) );
+    assert!( !is_command( "c1a0.cfg" ) );
+}
+
+#[test]
+fn cfg_report_lists_each_file()
+{
+    let dir = tempfile::tempdir().unwrap();
+    fs::write( dir.path().join( "good.bsp" ), empty_map() ).unwrap();
+    fs::write( dir.path().join( "locked.bsp" ), empty_map() ).unwrap();
+    fs::write( dir.path().join( "good.cfg" ), "mp_flashlight 1\nmp_timelimit 30\n" ).unwrap();
+    // A folder in the way of the cfg can't be read or written
+    fs::create_dir( dir.path().join( "locked.cfg" ) ).unwrap();
+    // Removing by name needs no CVar list to check the values against
+    let cfg = Cfg
+    {
+        cvars: "mp_flashlight".into(),
+        writetype: WriteType::UNSET,
+        is_skillcfg: false,
+        bspdir: dir.path().to_path_buf(),
+        bspwhitelist: vec![]
+    };
+
+    let report = cfg.create_with_backup( &BackupStore::new( dir.path().join( "backups" ), 5 ), false ).unwrap();
+    assert_eq!( report.files.len(), 2 );
+    assert_eq!( report.succeeded(), 1 );
+    assert!( report.failures().all( |f| f.path.ends_with( "locked.cfg" ) ) );
+    assert!( report.summary( "CFG" ).starts_with( "Processed 1 CFG file(s).\n\nFailed:\n" ) );
+    assert_eq!( fs::read_to_string( dir.path().join( "good.cfg" ) ).unwrap(), "mp_timelimit 30\n" );
+
+    assert_eq!( create_motd( " \n", dir.path() ).unwrap_err().kind(), std::io::ErrorKind::InvalidData );
+}
+
+#[test]
+fn write_types_go_by_cvar_name()
+{
+    let before = "// Map settings\nmp_flashlight 0 // dark map\nmp_timelimit 30\n";
+    let content = "MP_FLASHLIGHT 1\nmp_allowmonsterinfo 1\n";
+
+    assert_eq!( WriteType::SET.apply( Some( before ), content ).unwrap(), "// Map settings\nmp_flashlight 1 // dark map\nmp_timelimit 30\nmp_allowmonsterinfo 1\n" );
+    assert_eq!( WriteType::MERGE.apply( Some( before ), content ).unwrap(), "// Map settings\nmp_flashlight 0 // dark map\nmp_timelimit 30\nmp_allowmonsterinfo 1\n" );
+    assert_eq!( WriteType::UNSET.apply( Some( before ), "mp_flashlight\n" ).unwrap(), "// Map settings\nmp_timelimit 30\n" );
+    // Removing goes by value too, unlike unsetting
+    assert_eq!( WriteType::REMOVE.apply( Some( before ), "mp_flashlight 1\n" ).unwrap(), before );
+    assert_eq!( WriteType::REMOVE.apply( Some( before ), "mp_flashlight 0\n" ).unwrap(), "// Map settings\nmp_timelimit 30\n" );
+    // Setting on a missing file creates it, unsetting leaves it missing
+    assert_eq!( WriteType::SET.apply( None, content ).unwrap(), content );
+    assert_eq!( WriteType::UNSET.apply( None, content ), None );
+}
+
+#[test]
+fn minimize_and_overrides_go_by_the_last_value()
+{
+    let defaults = CfgDocument::parse( "mp_flashlight 0\nmp_timelimit 20\nsv_gravity 800\n" );
+    let text = "// Dark map\nmp_flashlight 1\nMP_FLASHLIGHT 0\nmp_timelimit 30\nsv_gravity 800\nmp_custom 1\n";
+
+    let overrides: Vec<_> = CfgDocument::parse( text ).overrides( &defaults ).iter().map( |c| c.to_string() ).collect();
+    assert_eq!( overrides, ["mp_timelimit 30", "mp_custom 1"] );
+    // Every line of a CVar goes once its last value is the default
+    let mut doc = CfgDocument::parse( text );
+    assert_eq!( doc.minimize( &defaults ), 3 );
+    assert_eq!( doc.to_string(), "// Dark map\nmp_timelimit 30\nmp_custom 1\n" );
+
+    assert_eq!( WriteType::MINIMIZE.apply( Some( text ), &defaults.to_string() ).unwrap(), doc.to_string() );
+    assert_eq!( WriteType::MINIMIZE.apply( None, &defaults.to_string() ), None );
+}
+
+#[test]
+fn audit_finds_companions_references_and_orphans()
+{
+    let game = tempfile::tempdir().unwrap();
+    let maps = game.path().join( "maps" );
+    fs::create_dir_all( game.path().join( "models/stadium" ) ).unwrap();
+    fs::create_dir( &maps ).unwrap();
+
+    fs::write( maps.join( "stadium.bsp" ), empty_map() ).unwrap();
+    fs::write( maps.join( "stadium.cfg" ), "globalmodellist stadium.gmr\nglobalsoundlist stadium.gsr\n" ).unwrap();
+    fs::write( maps.join( "STADIUM_motd.txt" ), "Welcome" ).unwrap();
+    fs::write( game.path().join( "models/stadium/stadium.gmr" ), "" ).unwrap();
+    fs::write( maps.join( "gone_skl.cfg" ), "" ).unwrap();
+    fs::write( maps.join( "gone.res" ), "" ).unwrap();
+    fs::write( maps.join( "readme.txt" ), "" ).unwrap();
+
+    let audit = Audit::run( &maps ).unwrap();
+    let map = &audit.maps[0];
+    assert_eq!( audit.maps.len(), 1 );
+    assert!( map.cfg && map.motd && !map.skill_cfg && !map.res );
+    assert!( map.error.is_none() );
+    // The model list is in the map's own models folder, the sound list is nowhere
+    let found: Vec<_> = map.references.iter().map( |r| ( r.cvar.as_str(), r.found ) ).collect();
+    assert_eq!( found, [( "globalmodellist", true ), ( "globalsoundlist", false )] );
+    assert_eq!( audit.missing_references().count(), 1 );
+    assert_eq!( audit.orphans, ["gone.res", "gone_skl.cfg"] );
+}
+
+#[test]
+fn transaction_puts_back_every_file()
+{
+    let dir = tempfile::tempdir().unwrap();
+    let ( old, new, busy ) = ( dir.path().join( "old.cfg" ), dir.path().join( "new.cfg" ), dir.path().join( "busy.cfg" ) );
+    fs::write( &old, "mp_flashlight 0\n" ).unwrap();
+    // A folder with files in it can't be replaced by a file, so the last change fails
+    fs::create_dir( &busy ).unwrap();
+    fs::write( busy.join( "keep" ), "" ).unwrap();
+
+    let change = |path: &PathBuf, before: Option<&str>, after: &str| FileChange
+    {
+        path: path.clone(),
+        before: before.map( str::to_owned ),
+        after: Some( after.to_owned() ),
+        encoding: Default::default()
+    };
+
+    let changes = vec![change( &old, Some( "mp_flashlight 0\n" ), "mp_flashlight 1\n" ), change( &new, None, "mp_timelimit 30\n" ), change( &busy, None, "" )];
+    assert!( Transaction::new( changes ).commit().is_err() );
+
+    assert_eq!( fs::read_to_string( &old ).unwrap(), "mp_flashlight 0\n" );
+    assert!( !new.exists() && busy.join( "keep" ).exists() );
+
+    let mut left: Vec<_> = fs::read_dir( dir.path() ).unwrap().map( |e| e.unwrap().file_name() ).collect();
+    left.sort();
+    assert_eq!( left, ["busy.cfg", "old.cfg"] );
+    // Nothing is touched when a file can't even be staged
+    let changes = vec![change( &old, Some( "mp_flashlight 0\n" ), "mp_flashlight 1\n" ), change( &dir.path().join( "gone/new.cfg" ), None, "" )];
+    assert!( Transaction::new( changes ).commit().is_err() );
+    assert_eq!( fs::read_to_string( &old ).unwrap(), "mp_flashlight 0\n" );
+    assert!( !dir.path().join( "old.cfg.tmp" ).exists() );
+}
+
+#[test]
+fn resource_check_finds_missing_and_shadowed_files()
+{
+    let install = tempfile::tempdir().unwrap();
+    let ( addon, stock ) = ( install.path().join( "svencoop_addon" ), install.path().join( "svencoop" ) );
+    fs::create_dir_all( addon.join( "maps" ) ).unwrap();
+    fs::create_dir_all( addon.join( "models" ) ).unwrap();
+    fs::create_dir_all( stock.join( "models" ) ).unwrap();
+    fs::write( addon.join( "models/barney.mdl" ), "" ).unwrap();
+    fs::write( stock.join( "models/barney.mdl" ), "" ).unwrap();
+    fs::write( stock.join( "models/stock.mdl" ), "" ).unwrap();
+
+    let entities = "{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"cycler\"\n\"model\" \"models/barney.mdl\"\n}\n\
+        {\n\"classname\" \"cycler\"\n\"model\" \"models/stock.mdl\"\n}\n{\n\"classname\" \"cycler\"\n\"model\" \"models/gone.mdl\"\n}\n";
+    fs::write( addon.join( "maps/test.bsp" ), map_with_entities( entities ) ).unwrap();
+    fs::write( addon.join( "maps/other.bsp" ), empty_map() ).unwrap();
+
+    let check = ResourceCheck::run_in( install.path(), &addon.join( "maps" ), &["test".into()] ).unwrap();
+    assert_eq!( check.searched, ["svencoop_addon", "svencoop"] );
+    assert_eq!( check.maps.len(), 1 );
+
+    let map = &check.maps[0];
+    let found = |path: &str| map.resources.iter().find( |r| r.path == path ).unwrap();
+    assert_eq!( found( "models/barney.mdl" ).found_in.as_deref(), Some( "svencoop_addon" ) );
+    assert_eq!( found( "models/barney.mdl" ).shadowed, ["svencoop"] );
+    assert_eq!( found( "models/stock.mdl" ).found_in.as_deref(), Some( "svencoop" ) );
+
+    let missing: Vec<_> = map.missing().map( |r| r.path.as_str() ).collect();
+    assert_eq!( missing, ["models/gone.mdl"] );
+    assert_eq!( check.missing_count(), 1 );
+}
+
+#[test]
+fn merge_invert_and_collapse_replacements()
+{
+    let models = |pairs: &[(&str, &str)]| -> Vec<Replacement>
+    {
+        pairs.iter().map( |( original, new )| Replacement::Models { original: original.to_string(), new: new.to_string() } ).collect()
+    };
+    let pairs = |replacements: &[Replacement]| -> Vec<(String, String)>
+    {
+        replacements.iter().map( |r| ( r.get_original().to_string(), r.get_new().to_string() ) ).collect()
+    };
+
+    let first = models( &[( "models/a.mdl", "models/b.mdl" ), ( "models/x.mdl", "models/y.mdl" )] );
+    let second = models( &[( "MODELS/A.mdl", "models/c.mdl" ), ( "models/x.mdl", "models/Y.mdl" ), ( "models/z.mdl", "models/w.mdl" )] );
+
+    let ( merged, conflicts ) = Replacement::merge( [( "first.gmr", first.as_slice() ), ( "second.gmr", second.as_slice() )] );
+    assert_eq!( merged.len(), 3 );
+    assert_eq!( merged[0].get_new(), "models/b.mdl" );
+    assert_eq!( conflicts.len(), 1 );
+    assert_eq!( conflicts[0].choices, [( "models/b.mdl".into(), "first.gmr".into() ), ( "models/c.mdl".into(), "second.gmr".into() )] );
+    // Two originals sharing a new file can only go back to the first
+    let ( inverted, conflicts ) = Replacement::invert( &models( &[( "models/a.mdl", "models/hd.mdl" ), ( "models/b.mdl", "models/hd.mdl" )] ) );
+    assert_eq!( pairs( &inverted ), [( "models/hd.mdl".into(), "models/a.mdl".into() )] );
+    assert_eq!( conflicts[0].original, "models/hd.mdl" );
+
+    let chain = models( &[( "models/a.mdl", "models/b.mdl" ), ( "models/b.mdl", "models/c.mdl" ), ( "models/p.mdl", "models/q.mdl" ), ( "models/q.mdl", "models/P.mdl" )] );
+    let ( collapsed, cycles ) = Replacement::collapse( &chain );
+    assert_eq!( pairs( &collapsed[..2] ), [( "models/a.mdl".into(), "models/c.mdl".into() ), ( "models/b.mdl".into(), "models/c.mdl".into() )] );
+    // Entries in a cycle stay as they were, and the cycle is listed once
+    assert_eq!( pairs( &collapsed[2..] ), pairs( &chain[2..] ) );
+    assert_eq!( cycles, [vec!["models/p.mdl".to_string(), "models/q.mdl".into()]] );
+}
+
+#[test]
+fn bsp_textures_are_marked_embedded_and_used()
+{
+    let miptex = |name: &str, size: i32, pixels: i32|
+    {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize( 16, 0 );
+        bytes.extend( [size, size, pixels, 0, 0, 0].iter().flat_map( |v| v.to_le_bytes() ) );
+        bytes
+    };
+    // Three textures, the second one left out by the compiler
+    let mut textures: Vec<u8> = [3, 16, -1, 56].iter().flat_map( |v: &i32| v.to_le_bytes() ).collect();
+    textures.extend( miptex( "brick", 16, 40 ) );
+    textures.extend( miptex( "sky", 32, 0 ) );
+    // One face, drawn with the texinfo that uses the third texture
+    let mut texinfo = vec![0u8; 40];
+    texinfo[32..36].copy_from_slice( &2i32.to_le_bytes() );
+    let faces = vec![0u8; 20];
+
+    let mut map = empty_map();
+
+    for ( lump, bytes ) in [( 2, &textures ), ( 6, &texinfo ), ( 7, &faces )]
+    {
+        let offset = map.len() as u32;
+        map[4 + lump * 8..][..4].copy_from_slice( &offset.to_le_bytes() );
+        map[8 + lump * 8..][..4].copy_from_slice( &( bytes.len() as u32 ).to_le_bytes() );
+        map.extend( bytes );
+    }
+
+    let found: Vec<_> = Bsp::from_bytes( map.clone() ).unwrap()
+        .textures()
+        .unwrap()
+        .into_iter()
+        .map( |t| ( t.name, t.width, t.embedded, t.used ) )
+    .collect();
+
+    assert_eq!( found, [( "brick".into(), 16, true, false ), ( "sky".into(), 32, false, true )] );
+    // A count that doesn't fit in the lump is refused
+    let textures_at = u32::from_le_bytes( map[20..24].try_into().unwrap() ) as usize;
+    map[textures_at..textures_at + 4].copy_from_slice( &100i32.to_le_bytes() );
+    assert!( Bsp::from_bytes( map ).unwrap().textures().is_err() );
+}
@@ -0,0 +1,134 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fs,
+    io
+};
+
+use crate::
+{
+    diff::FileChange,
+    report::
+    {
+        FileReport,
+        Outcome,
+        Report
+    },
+    utils::
+    {
+        temp_path,
+        write_atomic,
+        write_synced
+    }
+};
+/// A batch of file changes that are applied together or not at all.
+pub struct Transaction
+{
+    changes: Vec<FileChange>
+}
+
+impl Transaction
+{
+    pub fn new(changes: Vec<FileChange>) -> Self
+    {
+        Self { changes }
+    }
+    /// Applies every change. All new content is written to temporary files first, so a failed write leaves
+    /// every file untouched. If replacing a file then fails, the files already replaced are put back.
+    pub fn commit(self) -> io::Result<Report>
+    {
+        let ( changes, unchanged ): ( Vec<_>, Vec<_> ) = self.changes.into_iter().partition( FileChange::is_change );
+
+        if let Err( e ) = Self::stage( &changes )
+        {
+            Self::discard( &changes );
+            return Err( e );
+        }
+
+        let mut report: Report = unchanged
+            .into_iter()
+            .map( |c| FileReport::new( c.path, Outcome::Skipped( "Already up to date".into() ) ) )
+        .collect();
+
+        for ( i, change ) in changes.iter().enumerate()
+        {
+            let result =
+            match change.after
+            {
+                Some( _ ) => fs::rename( temp_path( &change.path ), &change.path ).map( |()| Outcome::Written ),
+                None => fs::remove_file( &change.path ).map( |()| Outcome::Deleted )
+            };
+
+            match result
+            {
+                Ok( outcome ) => report.push( FileReport::new( change.path.clone(), outcome ) ),
+                Err( e ) =>
+                {
+                    Self::discard( &changes[i..] );
+                    Self::rollback( &changes[..i] );
+
+                    return Err( io::Error::new( e.kind(),
+                        format!( "Failed to write {}: {e}\nNo files were changed.", change.path.display() ) ) );
+                }
+            }
+        }
+
+        Ok( report )
+    }
+
+    fn stage(changes: &[FileChange]) -> io::Result<()>
+    {
+        for change in changes
+        {
+            if let Some( after ) = &change.after
+            {
+                change.encoding.encode( after ).and_then( |bytes| write_synced( &temp_path( &change.path ), bytes ) ).map_err( |e| io::Error::new( e.kind(),
+                    format!( "Failed to write {}: {e}\nNo files were changed.", change.path.display() ) ) )?;
+            }
+        }
+
+        Ok( () )
+    }
+    /// Removes the temporary files of changes that were not applied.
+    fn discard(changes: &[FileChange])
+    {
+        for change in changes.iter().filter( |c| c.after.is_some() )
+        {
+            let _ = fs::remove_file( temp_path( &change.path ) );
+        }
+    }
+    /// Puts back the content the files had before the transaction.
+    fn rollback(changes: &[FileChange])
+    {
+        for change in changes
+        {
+            let result =
+            match &change.before
+            {
+                Some( before ) => change.encoding.encode( before ).and_then( |bytes| write_atomic( &change.path, bytes ) ),
+                None => fs::remove_file( &change.path )
+            };
+
+            if let Err( e ) = result
+            {
+                eprintln!( "Failed to roll back {}: {e}", change.path.display() );
+            }
+        }
+    }
+}
@@ -193,6 +193,28 @@ pub fn dir_contains_type(dir: &Path, ext: &str) -> bool
         Err( _ ) => false
     }
 }
+/// Levenshtein distance between two strings, ignoring ASCII case.
+pub fn edit_distance(a: &str, b: &str) -> usize
+{
+    let a: Vec<_> = a.chars().map( |c| c.to_ascii_lowercase() ).collect();
+    let b: Vec<_> = b.chars().map( |c| c.to_ascii_lowercase() ).collect();
+    let mut prev: Vec<_> = ( 0..=b.len() ).collect();
+
+    for ( i, ca ) in a.iter().enumerate()
+    {
+        let mut row = vec![i + 1; b.len() + 1];
+
+        for ( j, cb ) in b.iter().enumerate()
+        {
+            let cost = usize::from( ca != cb );
+            row[j + 1] = ( prev[j] + cost ).min( prev[j + 1] + 1 ).min( row[j] + 1 );
+        }
+
+        prev = row;
+    }
+
+    prev[b.len()]
+}
 /// Line-by-line reader
 pub fn read_trimmed_lines(path: &Path) -> Option<Vec<String>>
 {
@@ -209,23 +231,44 @@ pub fn read_trimmed_lines(path: &Path) -> Option<Vec<String>>
 /// Line-by-line writer for a given filename and extension.
 pub fn write_lines<L: std::fmt::Display>(filename: &str, ext: &str, lines: &[L]) -> std::io::Result<()>
 {
-    use std::io::Write;
-
     if lines.is_empty()
     {
         return Err( std::io::Error::new( std::io::ErrorKind::InvalidData, "Lines collection is empty." ) );
     }
 
-    let mut file = std::fs::OpenOptions::new()
-        .write( true )
-        .create( true )
-        .truncate( true )
-    .open( format!( "{filename}.{ext}" ) )?;
+    let content: String = lines.iter().map( |l| format!( "{l}\n" ) ).collect();
 
-    for l in lines
+    write_atomic( Path::new( &format!( "{filename}.{ext}" ) ), content )
+}
+/// Temporary file used while writing `path`. It sits next to it so the rename never crosses drives.
+pub fn temp_path(path: &Path) -> PathBuf
+{
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push( ".tmp" );
+
+    path.with_file_name( name )
+}
+/// Writes and flushes a file to disk, so a rename after it can't leave a half written file.
+pub fn write_synced(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()>
+{
+    use std::io::Write;
+
+    let mut file = std::fs::File::create( path )?;
+    file.write_all( contents.as_ref() )?;
+
+    file.sync_all()
+}
+/// Replaces the whole file or nothing: the contents are written to a temporary file that is then renamed over `path`.
+/// If the write fails or the app crashes, the old file is left as it was.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()>
+{
+    let tmp = temp_path( path );
+    let result = write_synced( &tmp, contents ).and_then( |()| std::fs::rename( &tmp, path ) );
+
+    if result.is_err()
     {
-        writeln!( file, "{l}" )?;// Write can fail here, throw to prevent corruption.
+        let _ = std::fs::remove_file( &tmp );
     }
-    
-    Ok( () )
+
+    result
 }
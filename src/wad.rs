@@ -0,0 +1,324 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs::
+    {
+        self,
+        File
+    },
+    io::
+    {
+        self,
+        BufReader,
+        Read,
+        Seek,
+        SeekFrom
+    },
+    path::Path
+};
+
+pub const EXT_WAD: &str = "wad";
+const WAD_ID: &[u8; 4] = b"WAD3";
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 32;
+/// Name, size and the offsets of the four mip levels at the start of a miptex.
+const MIPTEX_HEADER_SIZE: usize = 40;
+/// Textures whose name starts with this draw palette index 255 as see-through.
+const TRANSPARENT_PREFIX: char = '{';
+/// What a lump in a WAD holds.
+#[derive( Clone, Copy, Debug, PartialEq )]
+pub enum LumpKind
+{
+    /// A plain image, like the ones in `gfx.wad`.
+    Picture,
+    /// A world texture with mip levels.
+    MipTex,
+    Font,
+    Other( u8 )
+}
+
+impl From<u8> for LumpKind
+{
+    fn from(kind: u8) -> Self
+    {
+        match kind
+        {
+            0x42 => Self::Picture,
+            0x43 => Self::MipTex,
+            0x46 => Self::Font,
+            other => Self::Other( other )
+        }
+    }
+}
+
+impl LumpKind
+{   /// Where the width and height are in the lump. Pictures and fonts start with their size, textures have their name first.
+    fn size_at(self) -> Option<usize>
+    {
+        match self
+        {
+            Self::MipTex => Some( 16 ),
+            Self::Picture | Self::Font => Some( 0 ),
+            Self::Other( _ ) => None
+        }
+    }
+}
+
+impl Display for LumpKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::Picture => write!( f, "picture" ),
+            Self::MipTex => write!( f, "miptex" ),
+            Self::Font => write!( f, "font" ),
+            Self::Other( kind ) => write!( f, "unknown ({kind:#04x})" )
+        }
+    }
+}
+/// An entry in the WAD directory.
+#[derive( Clone, Debug )]
+pub struct WadLump
+{
+    pub name: String,
+    pub kind: LumpKind,
+    pub offset: u32,
+    /// Bytes the lump takes in the file.
+    pub disk_size: u32,
+    /// Bytes the lump takes once decompressed. WAD3 files are never compressed in practice.
+    pub size: u32,
+    pub compressed: bool,
+    /// Size of the image, zero for lumps that are not pictures, textures or fonts.
+    pub width: u32,
+    pub height: u32
+}
+/// An image decoded to 8-bit RGBA, row by row from the top.
+#[derive( Clone, Debug )]
+pub struct Image
+{
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>
+}
+/// A WAD3 texture file, read into memory.
+#[derive( Clone, Debug )]
+pub struct Wad
+{
+    pub lumps: Vec<WadLump>,
+    data: Vec<u8>
+}
+
+impl Wad
+{
+    pub fn open(path: &Path) -> io::Result<Self>
+    {
+        Self::from_bytes( fs::read( path )? )
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self>
+    {
+        let ( count, directory ) = read_header( data.get( ..HEADER_SIZE ).unwrap_or_default(), data.len() as u64 )?;
+
+        let lumps = data[directory..][..count * ENTRY_SIZE]
+            .chunks_exact( ENTRY_SIZE )
+            .map( |entry|
+            {
+                let mut lump = read_entry( entry );
+
+                if let Some( at ) = lump.kind.size_at()
+                && let Some( size ) = data.get( lump.offset as usize + at.. ).and_then( |b| b.get( ..8 ) )
+                {
+                    ( lump.width, lump.height ) = read_size( size );
+                }
+
+                lump
+            })
+        .collect();
+
+        Ok( Self { lumps, data } )
+    }
+    /// Reads only the header and lump directory of a WAD, for when the textures themselves aren't needed.
+    pub fn read_directory(path: &Path) -> io::Result<Vec<WadLump>>
+    {
+        let mut file = BufReader::new( File::open( path )? );
+        let length = file.get_ref().metadata()?.len();
+
+        let mut header = vec![0; HEADER_SIZE.min( length as usize )];
+        file.read_exact( &mut header )?;
+        let ( count, directory ) = read_header( &header, length )?;
+
+        let mut entries = vec![0; count * ENTRY_SIZE];
+        file.seek( SeekFrom::Start( directory as u64 ) )?;
+        file.read_exact( &mut entries )?;
+
+        let mut lumps: Vec<_> = entries.chunks_exact( ENTRY_SIZE ).map( read_entry ).collect();
+
+        for lump in &mut lumps
+        {
+            let mut size = [0; 8];
+
+            if let Some( at ) = lump.kind.size_at()
+            && file.seek( SeekFrom::Start( lump.offset as u64 + at as u64 ) ).is_ok()
+            && file.read_exact( &mut size ).is_ok()
+            {
+                ( lump.width, lump.height ) = read_size( &size );
+            }
+        }
+
+        Ok( lumps )
+    }
+    /// Finds a lump by name, ignoring case as the engine does.
+    pub fn find(&self, name: &str) -> Option<&WadLump>
+    {
+        self.lumps.iter().find( |l| l.name.eq_ignore_ascii_case( name ) )
+    }
+    /// The bytes of a lump as stored in the file.
+    pub fn lump_data(&self, lump: &WadLump) -> io::Result<&[u8]>
+    {
+        self.data
+            .get( lump.offset as usize..lump.offset as usize + lump.disk_size as usize )
+        .ok_or_else( || invalid( &format!( "{} is outside the file.", lump.name ) ) )
+    }
+    /// Decodes a texture to RGBA at the given mip level, 0 being full size and 3 an eighth.
+    pub fn decode(&self, lump: &WadLump, mip: usize) -> io::Result<Image>
+    {
+        if lump.kind != LumpKind::MipTex || lump.compressed
+        {
+            return Err( invalid( &format!( "{} is not an uncompressed texture.", lump.name ) ) );
+        }
+
+        decode_miptex( self.lump_data( lump )?, mip )
+    }
+}
+/// Decodes a miptex, as stored in WADs and BSP texture lumps, to RGBA at the given mip level (0 to 3) using its palette.
+/// Textures named `{...` show palette index 255 as see-through.
+pub fn decode_miptex(bytes: &[u8], mip: usize) -> io::Result<Image>
+{
+    if bytes.len() < MIPTEX_HEADER_SIZE || mip > 3
+    {
+        return Err( invalid( "Texture is too small to be a miptex." ) );
+    }
+
+    let read_u32 = |at: usize| u32::from_le_bytes( [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]] );
+
+    let name = c_string( &bytes[..16] );
+    let ( full_width, full_height ) = ( read_u32( 16 ), read_u32( 20 ) );
+    let offsets: Vec<_> = ( 0..4 ).map( |i| read_u32( 24 + i * 4 ) as usize ).collect();
+
+    if offsets[0] == 0
+    {
+        return Err( invalid( &format!( "{name} has no image data, it is stored in a WAD." ) ) );
+    }
+
+    if full_width == 0 || full_height == 0 || full_width % 16 != 0 || full_height % 16 != 0 || full_width > 4096 || full_height > 4096
+    {
+        return Err( invalid( &format!( "{name} has an invalid size of {full_width}x{full_height}." ) ) );
+    }
+
+    let ( width, height ) = ( full_width >> mip, full_height >> mip );
+    // The palette follows the smallest mip level, after its colour count
+    let palette_at = offsets[3] + ( full_width as usize / 8 ) * ( full_height as usize / 8 ) + 2;
+    let pixels_at = offsets[mip];
+    let pixel_count = width as usize * height as usize;
+
+    let ( Some( pixels ), Some( palette ) ) = ( bytes.get( pixels_at..pixels_at + pixel_count ), bytes.get( palette_at..palette_at + 256 * 3 ) )
+    else
+    {
+        return Err( invalid( &format!( "{name} is cut short." ) ) );
+    };
+
+    let transparent = name.starts_with( TRANSPARENT_PREFIX );
+
+    let rgba = pixels
+        .iter()
+        .flat_map( |&index|
+        {
+            let colour = &palette[index as usize * 3..][..3];
+            let alpha = if transparent && index == 255 { 0 } else { 255 };
+            [colour[0], colour[1], colour[2], alpha]
+        })
+    .collect();
+
+    Ok( Image { width, height, rgba } )
+}
+/// The smallest mip level that is still at least `size` pixels on its longest side, or full size for textures smaller than that.
+pub fn mip_for(width: u32, height: u32, size: u32) -> usize
+{
+    ( 0..=3 ).rev().find( |&mip| width.max( height ) >> mip >= size ).unwrap_or( 0 )
+}
+/// The lump count and where the directory starts, checked to lie inside a file of `length` bytes.
+fn read_header(header: &[u8], length: u64) -> io::Result<(usize, usize)>
+{
+    if header.len() < HEADER_SIZE || &header[..4] != WAD_ID
+    {
+        return Err( io::Error::new( io::ErrorKind::InvalidInput, "Not a valid WAD3 file." ) );
+    }
+
+    let count = read_u32( &header[4..8] ) as u64;
+    let directory = read_u32( &header[8..12] ) as u64;
+
+    if directory + count * ENTRY_SIZE as u64 > length
+    {
+        return Err( invalid( "The lump directory is outside the file." ) );
+    }
+
+    Ok( ( count as usize, directory as usize ) )
+}
+/// A directory entry, without the image size that is stored in the lump itself.
+fn read_entry(entry: &[u8]) -> WadLump
+{
+    WadLump
+    {
+        name: c_string( &entry[16..32] ),
+        kind: LumpKind::from( entry[12] ),
+        offset: read_u32( &entry[0..4] ),
+        disk_size: read_u32( &entry[4..8] ),
+        size: read_u32( &entry[8..12] ),
+        compressed: entry[13] != 0,
+        width: 0,
+        height: 0
+    }
+}
+
+fn read_size(bytes: &[u8]) -> (u32, u32)
+{
+    ( read_u32( &bytes[0..4] ), read_u32( &bytes[4..8] ) )
+}
+
+fn read_u32(bytes: &[u8]) -> u32
+{
+    u32::from_le_bytes( [bytes[0], bytes[1], bytes[2], bytes[3]] )
+}
+/// Text of a fixed size, zero padded string field.
+fn c_string(bytes: &[u8]) -> String
+{
+    let end = bytes.iter().position( |&b| b == 0 ).unwrap_or( bytes.len() );
+    String::from_utf8_lossy( &bytes[..end] ).trim().to_owned()
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new( io::ErrorKind::InvalidData, message.to_owned() )
+}
@@ -0,0 +1,240 @@
+/*
+	CFGBeast Version 3.0
+
+Copyright (C) 2025 Outerbeast
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+use std::
+{
+    fmt::
+    {
+        self,
+        Display
+    },
+    fs,
+    io,
+    path::Path
+};
+
+use crate::
+{
+    replacements::
+    {
+        GameRoots,
+        Replacement
+    },
+    utils::HasExtension
+};
+
+pub const EXT_WAV: &str = "wav";
+/// Sound formats the game loads. The other extensions in `EXTS_SOUNDS` are never played as sounds.
+pub const PLAYABLE_SOUNDS: [&str; 4] = ["wav", "ogg", "mp3", "flac"];
+/// Sample rates the engine mixes at, others are resampled badly or play at the wrong pitch.
+pub const SAMPLE_RATES: [u32; 3] = [11025, 22050, 44100];
+const FORMAT_PCM: u16 = 1;
+/// Format and loop points of a RIFF WAVE file.
+#[derive( Clone, Debug )]
+pub struct Wav
+{
+    /// Codec tag from the `fmt ` chunk, 1 is uncompressed PCM.
+    pub format: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits: u16,
+    /// Length of the sample data in bytes.
+    pub data_len: u32,
+    /// Points in the `cue ` chunk. The engine loops a sound from its first cue point.
+    pub cue_points: usize
+}
+
+impl Wav
+{
+    pub fn read(path: &Path) -> io::Result<Self>
+    {
+        Self::from_bytes( &fs::read( path )? )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self>
+    {
+        if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE"
+        {
+            return Err( invalid( "Not a RIFF WAVE file." ) );
+        }
+
+        let u16_at = |at: usize| u16::from_le_bytes( [bytes[at], bytes[at + 1]] );
+        let u32_at = |at: usize| u32::from_le_bytes( [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]] );
+
+        let mut fmt = None;
+        let mut data_len = None;
+        let mut cue_points = 0;
+        let mut at = 12;
+
+        while at + 8 <= bytes.len()
+        {
+            let ( id, len ) = ( &bytes[at..at + 4], u32_at( at + 4 ) as usize );
+            let body = at + 8;
+            // A truncated last chunk is common, read what is there
+            let available = len.min( bytes.len() - body );
+
+            match id
+            {
+                b"fmt " if available >= 16 => fmt = Some( ( u16_at( body ), u16_at( body + 2 ), u32_at( body + 4 ), u16_at( body + 14 ) ) ),
+                b"data" => data_len = Some( available as u32 ),
+                b"cue " if available >= 4 => cue_points = u32_at( body ) as usize,
+                _ => { }
+            }
+            // Chunks are padded to an even length. A length too large to add up can't be followed by another chunk
+            match body.checked_add( len ).and_then( |end| end.checked_add( len & 1 ) )
+            {
+                Some( next ) => at = next,
+                None => break
+            }
+        }
+
+        let ( format, channels, sample_rate, bits ) = fmt.ok_or_else( || invalid( "The file has no format chunk." ) )?;
+        let data_len = data_len.ok_or_else( || invalid( "The file has no sample data." ) )?;
+
+        Ok( Self { format, channels, sample_rate, bits, data_len, cue_points } )
+    }
+
+    pub fn loops(&self) -> bool
+    {
+        self.cue_points > 0
+    }
+    /// Length in seconds.
+    pub fn duration(&self) -> f32
+    {
+        let bytes_per_second = self.sample_rate as f32 * self.channels as f32 * ( self.bits as f32 / 8.0 );
+        if bytes_per_second > 0.0 { self.data_len as f32 / bytes_per_second } else { 0.0 }
+    }
+
+    pub fn codec(&self) -> &'static str
+    {
+        match self.format
+        {
+            FORMAT_PCM => "PCM",
+            2 => "MS ADPCM",
+            3 => "IEEE float",
+            6 => "A-law",
+            7 => "mu-law",
+            0x11 => "IMA ADPCM",
+            0x55 => "MP3",
+            0xFFFE => "extensible",
+            _ => "unknown codec"
+        }
+    }
+    /// Why the engine would fail to play the sound or play it wrong.
+    pub fn problems(&self) -> Vec<String>
+    {
+        let mut problems = vec![];
+
+        if self.format != FORMAT_PCM
+        {
+            problems.push( format!( "Uses {} (format {}), only uncompressed PCM plays", self.codec(), self.format ) );
+        }
+
+        if self.channels != 1
+        {
+            problems.push( format!( "Has {} channels, only mono plays", self.channels ) );
+        }
+
+        if self.bits != 8 && self.bits != 16
+        {
+            problems.push( format!( "Is {}-bit, only 8 and 16-bit play", self.bits ) );
+        }
+
+        if !SAMPLE_RATES.contains( &self.sample_rate )
+        {
+            problems.push( format!( "Has an odd sample rate of {} Hz, use 11025, 22050 or 44100", self.sample_rate ) );
+        }
+
+        problems
+    }
+}
+
+impl Display for Wav
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let channels = if self.channels == 1 { "mono".into() } else { format!( "{} channels", self.channels ) };
+        let looping = if self.loops() { ", loops" } else { "" };
+
+        write!( f, "{} {}-bit {channels} {} Hz, {:.2} s{looping}", self.codec(), self.bits, self.sample_rate, self.duration() )
+    }
+}
+/// Why the game won't load a sound with this extension, if it won't.
+pub fn unplayable(path: &Path) -> Option<String>
+{
+    ( !path.has_extension( &PLAYABLE_SOUNDS ) )
+        .then( || format!( "The game does not load .{} sounds", path.extension().unwrap_or_default().to_string_lossy() ) )
+}
+/// Problems with a sound file itself: an extension the game won't load, or a WAV it can't play properly.
+pub fn check_sound(path: &Path) -> Vec<String>
+{
+    if let Some( problem ) = unplayable( path )
+    {
+        return vec![problem];
+    }
+
+    if !path.has_extension( &[EXT_WAV] )
+    {
+        return vec![];
+    }
+
+    match Wav::read( path )
+    {
+        Ok( wav ) => wav.problems(),
+        Err( e ) => vec![format!( "Could not read {}: {e}", path.display() )]
+    }
+}
+/// Checks the new sound of a replacement, and that it loops the same way as the original.
+/// Sounds that loop are played until stopped, so a mismatch leaves a sound cut short or playing forever.
+pub fn replacement_warnings(original: &Path, new: &Path) -> Vec<String>
+{
+    let mut warnings = check_sound( new );
+
+    if let ( Ok( original ), Ok( new ) ) = ( Wav::read( original ), Wav::read( new ) )
+    && original.loops() != new.loops()
+    {
+        warnings.push( if original.loops() { "The original loops, this sound doesn't" } else { "This sound loops, the original doesn't" }.into() );
+    }
+
+    warnings
+}
+/// Checks the sounds of loaded replacements, finding the files in the game's content folders.
+/// Sounds that can't be found are only checked by extension. Each warning is prefixed with the new sound's path.
+pub fn check_replacements(replacements: &[Replacement], roots: &GameRoots) -> Vec<String>
+{
+    replacements
+        .iter()
+        .filter( |r| matches!( r, Replacement::Sounds { .. } ) )
+        .flat_map( |r|
+        {
+            let warnings =
+            match ( roots.locate( r.get_original() ), roots.locate( r.get_new() ) )
+            {
+                ( Some( original ), Some( new ) ) => replacement_warnings( &original, &new ),
+                ( None, Some( new ) ) => check_sound( &new ),
+                ( _, None ) => unplayable( Path::new( r.get_new() ) ).into_iter().collect()
+            };
+
+            warnings.into_iter().map( |w| format!( "{}: {w}", r.get_new() ) )
+        })
+    .collect()
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new( io::ErrorKind::InvalidData, message.to_owned() )
+}